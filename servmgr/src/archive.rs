@@ -0,0 +1,331 @@
+use std::{fmt, fs, io::{self, BufWriter, Read, Seek, Write}, path::{Path, PathBuf}};
+use clap::ValueEnum;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use xz2::{read::XzDecoder, write::XzEncoder};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Formats backups can be written in, picked by file extension when reading them back
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarZst,
+    TarXz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 4] = [ArchiveFormat::TarGz, ArchiveFormat::TarZst, ArchiveFormat::TarXz, ArchiveFormat::Zip];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => ".tar.gz",
+            ArchiveFormat::TarZst => ".tar.zst",
+            ArchiveFormat::TarXz => ".tar.xz",
+            ArchiveFormat::Zip => ".zip",
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<ArchiveFormat> {
+        ArchiveFormat::ALL.into_iter().find(|format| file_name.ends_with(format.extension()))
+    }
+
+    fn default_level(&self) -> u32 {
+        match self {
+            ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::Zip => 6,
+            ArchiveFormat::TarZst => 3,
+        }
+    }
+
+    fn level_range(&self) -> (u32, u32) {
+        match self {
+            ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::Zip => (0, 9),
+            ArchiveFormat::TarZst => (1, 22),
+        }
+    }
+
+    /// The compression level to use, the format's default if none was given
+    pub fn check_level(&self, level: Option<u32>) -> io::Result<u32> {
+        let (min_level, max_level) = self.level_range();
+        let level = level.unwrap_or(self.default_level());
+        if !(min_level..=max_level).contains(&level) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Compression level {} is out of range for this format ({}-{})", level, min_level, max_level)));
+        }
+        Ok(level)
+    }
+}
+
+/// How far along writing an archive is, passed to the progress callback after every chunk written
+pub struct ArchiveProgress<'a> {
+    pub current_file: &'a Path,
+    pub files_done: u64,
+    pub bytes_done: u64,
+}
+
+/// An archive failure along with the file that was being archived or extracted when it happened
+#[derive(Debug)]
+pub struct ArchiveError {
+    pub path: Option<PathBuf>,
+    pub source: io::Error,
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.display(), self.source),
+            None => write!(f, "{}", self.source),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<io::Error> for ArchiveError {
+    fn from(source: io::Error) -> ArchiveError {
+        ArchiveError { path: None, source }
+    }
+}
+
+impl From<zip::result::ZipError> for ArchiveError {
+    fn from(source: zip::result::ZipError) -> ArchiveError {
+        ArchiveError { path: None, source: io::Error::other(source) }
+    }
+}
+
+pub fn error_at(path: &Path) -> impl FnOnce(io::Error) -> ArchiveError + '_ {
+    move |source| ArchiveError { path: Some(path.to_path_buf()), source }
+}
+
+/// The SHA-256 of a file as it was written into a backup, by its path inside the backup
+pub struct FileDigest {
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Totals of a finished archive
+pub struct ArchiveSummary {
+    pub files: u64,
+    pub bytes: u64,
+    pub digests: Vec<FileDigest>,
+}
+
+pub fn hex_digest(hasher: Sha256) -> String {
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hashes bytes and reports them to the progress callback as they are read out of a file
+struct ProgressReader<'a, R: Read, F: FnMut(&ArchiveProgress)> {
+    inner: R,
+    hasher: Sha256,
+    current_file: &'a Path,
+    files_done: u64,
+    bytes_done: &'a mut u64,
+    on_progress: &'a mut F,
+}
+
+impl<R: Read, F: FnMut(&ArchiveProgress)> Read for ProgressReader<'_, R, F> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buffer)?;
+        self.hasher.update(&buffer[..read]);
+        *self.bytes_done += read as u64;
+        (self.on_progress)(&ArchiveProgress { current_file: self.current_file, files_done: self.files_done, bytes_done: *self.bytes_done });
+        Ok(read)
+    }
+}
+
+/// Every file and directory under `source_directory` in a stable order, as (path on disk, path inside the archive)
+pub fn collect_entries(source_directory: &Path, root_name: &Path) -> Result<Vec<(PathBuf, PathBuf)>, ArchiveError> {
+    let mut entries = vec![(source_directory.to_path_buf(), root_name.to_path_buf())];
+    let mut index = 0;
+
+    while index < entries.len() {
+        let (disk_path, archive_path) = entries[index].clone();
+        index += 1;
+        if !fs::symlink_metadata(&disk_path).map_err(error_at(&disk_path))?.is_dir() { continue; }
+
+        let mut children: Vec<fs::DirEntry> = fs::read_dir(&disk_path).map_err(error_at(&disk_path))?
+            .collect::<io::Result<_>>().map_err(error_at(&disk_path))?;
+        children.sort_by_key(|child| child.file_name());
+        entries.extend(children.into_iter().map(|child| (child.path(), archive_path.join(child.file_name()))));
+    }
+
+    Ok(entries)
+}
+
+/// Writes `source_directory` into a new archive at `destination` with every path relative to it, under `root_name`
+/// (e.g.) world/level.dat, world/region/r.0.0.mca
+pub fn write_archive(
+    source_directory: &Path,
+    root_name: &Path,
+    destination: &Path,
+    format: ArchiveFormat,
+    level: Option<u32>,
+    mut on_progress: impl FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, ArchiveError> {
+    let level = format.check_level(level)?;
+    let entries = collect_entries(source_directory, root_name)?;
+    let file = BufWriter::new(fs::File::create(destination).map_err(error_at(destination))?);
+    let mut summary = ArchiveSummary { files: 0, bytes: 0, digests: Vec::new() };
+
+    match format {
+        ArchiveFormat::TarGz => {
+            let encoder = GzEncoder::new(file, Compression::new(level));
+            let encoder = write_tar(encoder, &entries, &mut summary, &mut on_progress)?;
+            encoder.finish()?.flush()?;
+        },
+        ArchiveFormat::TarZst => {
+            let encoder = zstd::Encoder::new(file, level as i32)?;
+            let encoder = write_tar(encoder, &entries, &mut summary, &mut on_progress)?;
+            encoder.finish()?.flush()?;
+        },
+        ArchiveFormat::TarXz => {
+            let encoder = XzEncoder::new(file, level);
+            let encoder = write_tar(encoder, &entries, &mut summary, &mut on_progress)?;
+            encoder.finish()?.flush()?;
+        },
+        ArchiveFormat::Zip => write_zip(file, &entries, level, &mut summary, &mut on_progress)?,
+    }
+
+    Ok(summary)
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    entries: &[(PathBuf, PathBuf)],
+    summary: &mut ArchiveSummary,
+    on_progress: &mut impl FnMut(&ArchiveProgress),
+) -> Result<W, ArchiveError> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for (disk_path, archive_path) in entries {
+        let metadata = fs::symlink_metadata(disk_path).map_err(error_at(disk_path))?;
+        if !metadata.is_file() {
+            builder.append_path_with_name(disk_path, archive_path).map_err(error_at(disk_path))?;
+            continue;
+        }
+
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&metadata);
+        let file = fs::File::open(disk_path).map_err(error_at(disk_path))?;
+        let mut reader = ProgressReader {
+            inner: file.take(metadata.len()), hasher: Sha256::new(), current_file: disk_path,
+            files_done: summary.files, bytes_done: &mut summary.bytes, on_progress: &mut *on_progress };
+        builder.append_data(&mut header, archive_path, &mut reader).map_err(error_at(disk_path))?;
+        let sha256 = hex_digest(reader.hasher);
+        summary.digests.push(FileDigest { path: archive_path.clone(), size: metadata.len(), sha256 });
+        summary.files += 1;
+    }
+
+    builder.into_inner().map_err(ArchiveError::from)
+}
+
+fn write_zip<W: Write + Seek>(
+    writer: W,
+    entries: &[(PathBuf, PathBuf)],
+    level: u32,
+    summary: &mut ArchiveSummary,
+    on_progress: &mut impl FnMut(&ArchiveProgress),
+) -> Result<(), ArchiveError> {
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(Some(level as i64));
+
+    for (disk_path, archive_path) in entries {
+        let metadata = fs::symlink_metadata(disk_path).map_err(error_at(disk_path))?;
+        let archive_name = archive_path.to_string_lossy().into_owned();
+
+        if metadata.is_dir() {
+            zip.add_directory(archive_name, options).map_err(|err| ArchiveError { path: Some(disk_path.clone()), source: io::Error::other(err) })?;
+            continue;
+        }
+        if !metadata.is_file() { continue; } // zip has no portable way to store symlinks or special files
+
+        let file_options = options.large_file(metadata.len() > u32::MAX as u64);
+        zip.start_file(archive_name, file_options).map_err(|err| ArchiveError { path: Some(disk_path.clone()), source: io::Error::other(err) })?;
+        let file = fs::File::open(disk_path).map_err(error_at(disk_path))?;
+        let mut reader = ProgressReader {
+            inner: file, hasher: Sha256::new(), current_file: disk_path,
+            files_done: summary.files, bytes_done: &mut summary.bytes, on_progress: &mut *on_progress };
+        let size = io::copy(&mut reader, &mut zip).map_err(error_at(disk_path))?;
+        let sha256 = hex_digest(reader.hasher);
+        summary.digests.push(FileDigest { path: archive_path.clone(), size, sha256 });
+        summary.files += 1;
+    }
+
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// A single entry handed to the `for_each_entry` callback
+pub struct ArchiveEntry<'a> {
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_file: bool,
+    pub is_dir: bool,
+    pub reader: &'a mut dyn Read,
+}
+
+fn open_tar_stream(path: &Path, format: ArchiveFormat) -> io::Result<Box<dyn Read>> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::Decoder::with_buffer(file)?),
+        ArchiveFormat::TarXz => Box::new(XzDecoder::new(file)),
+        ArchiveFormat::Zip => return Err(io::Error::new(io::ErrorKind::InvalidInput, "zip archives are not tar streams")),
+    })
+}
+
+/// Streams through an archive without extracting it, the callback returns false to stop early
+pub fn for_each_entry(path: &Path, mut callback: impl FnMut(ArchiveEntry) -> io::Result<bool>) -> io::Result<()> {
+    let format = ArchiveFormat::from_file_name(&path.to_string_lossy())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown archive format: {}", path.display())))?;
+
+    if format == ArchiveFormat::Zip {
+        let mut zip = ZipArchive::new(io::BufReader::new(fs::File::open(path)?)).map_err(io::Error::other)?;
+        for index in 0..zip.len() {
+            let mut zip_file = zip.by_index(index).map_err(io::Error::other)?;
+            let Some(entry_path) = zip_file.enclosed_name() else { continue; };
+            let (size, is_dir) = (zip_file.size(), zip_file.is_dir());
+            let entry = ArchiveEntry { path: entry_path, size, is_file: !is_dir, is_dir, reader: &mut zip_file };
+            if !callback(entry)? { break; }
+        }
+        return Ok(());
+    }
+
+    let mut archive = tar::Archive::new(open_tar_stream(path, format)?);
+    for tar_entry in archive.entries()? {
+        let mut tar_entry = tar_entry?;
+        let entry_path = tar_entry.path()?.into_owned();
+        let entry_type = tar_entry.header().entry_type();
+        let size = tar_entry.header().size()?;
+        let entry = ArchiveEntry { path: entry_path, size, is_file: entry_type.is_file(), is_dir: entry_type.is_dir(), reader: &mut tar_entry };
+        if !callback(entry)? { break; }
+    }
+    Ok(())
+}
+
+/// Extracts an archive into `destination`, refusing entries that would land outside of it
+pub fn extract_archive(path: &Path, destination: &Path) -> Result<(), ArchiveError> {
+    let format = ArchiveFormat::from_file_name(&path.to_string_lossy())
+        .ok_or_else(|| ArchiveError::from(io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown archive format: {}", path.display()))))?;
+
+    if format == ArchiveFormat::Zip {
+        let mut zip = ZipArchive::new(io::BufReader::new(fs::File::open(path).map_err(error_at(path))?))?;
+        zip.extract(destination)?;
+        return Ok(());
+    }
+
+    let mut archive = tar::Archive::new(open_tar_stream(path, format).map_err(error_at(path))?);
+    archive.set_preserve_permissions(true);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path().map(|entry_path| entry_path.into_owned()).unwrap_or_default();
+        // unpack_in refuses entries that would escape the destination (absolute paths are made relative)
+        entry.unpack_in(destination).map_err(error_at(&entry_path))?;
+    }
+    Ok(())
+}
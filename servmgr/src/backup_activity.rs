@@ -0,0 +1,91 @@
+use std::{fs, hash::{DefaultHasher, Hash, Hasher}, io, path::{Path, PathBuf}};
+use chrono::{DateTime, Duration, Local};
+use crate::{archive, rcon::{self, RconClient}, server_log::{self, LogWatcher}};
+
+/// What the server logs when a player joins, (e.g.) "[Server thread/INFO]: Steve joined the game"
+const PLAYER_JOINED_MESSAGE: &str = " joined the game";
+
+/// A hash of the path, size and modification time of every file in the world, the server only writes to the world when
+/// it saves so an unchanged fingerprint means there is nothing new to back up
+pub fn world_fingerprint(world_path: &Path) -> io::Result<u64> {
+    let entries = archive::collect_entries(world_path, &PathBuf::new()).map_err(|err| io::Error::new(err.source.kind(), err.to_string()))?;
+
+    let mut hasher = DefaultHasher::new();
+    for (disk_path, relative_path) in entries {
+        let metadata = fs::symlink_metadata(&disk_path)?;
+        relative_path.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        metadata.modified()?.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Asks the server how many players are online, keeping one RCON session open between polls rather than filling the
+/// server log with a new connection every time, and follows the server log for players who join and leave between polls
+pub struct PlayerWatcher {
+    rcon_password: String,
+    rcon: Option<RconClient>,
+    log: LogWatcher,
+}
+
+impl PlayerWatcher {
+    pub fn new(rcon_password: &str) -> PlayerWatcher {
+        PlayerWatcher { rcon_password: String::from(rcon_password), rcon: None, log: LogWatcher::open(&server_log::latest_log_path()) }
+    }
+
+    /// How many players are online, at least one if someone joined since the last poll even if they already left, `None`
+    /// when the server can't be asked (e.g.) it is stopped or restarting
+    pub fn online_player_count(&mut self) -> Option<usize> {
+        let joined = self.log.read_new_lines().is_ok_and(|lines| lines.iter().any(|line| line.contains(PLAYER_JOINED_MESSAGE)));
+        if self.rcon.is_none() {
+            self.rcon = rcon::open_responding_session(Some(&self.rcon_password)).ok();
+        }
+        let list_response = self.rcon.as_mut()?.command("list");
+        match list_response {
+            Ok(list_response) => Some(rcon::parse_player_names(&list_response).len().max(joined as usize)),
+            Err(_) => {
+                self.rcon = None; // reconnects on the next poll
+                None
+            },
+        }
+    }
+}
+
+/// What happened since a schedule's last successful backup, used to skip runs that would back up an idle world again
+pub struct ActivityTracker {
+    last_backup: Option<(DateTime<Local>, Option<u64>)>,
+    players_seen: bool,
+}
+
+impl ActivityTracker {
+    /// Nothing is known about the time before the daemon started, so the first run always backs up
+    pub fn new() -> ActivityTracker {
+        ActivityTracker { last_backup: None, players_seen: true }
+    }
+
+    /// Records a player count, `None` when the server couldn't be asked, which is treated as players having been online
+    pub fn observe_players(&mut self, player_count: Option<usize>) {
+        if player_count.is_none_or(|player_count| player_count > 0) { self.players_seen = true; }
+    }
+
+    pub fn record_backup(&mut self, at: DateTime<Local>, fingerprint: Option<u64>) {
+        self.last_backup = Some((at, fingerprint));
+        self.players_seen = false;
+    }
+
+    /// Why the run at `now` can be skipped, `None` when it has to back up, which it always does once `max_interval` has
+    /// passed since the last backup
+    pub fn skip_reason(&self, now: DateTime<Local>, max_interval: Duration, fingerprint: Option<u64>) -> Option<String> {
+        let (last_backup_at, last_fingerprint) = self.last_backup?;
+        if now.signed_duration_since(last_backup_at) >= max_interval { return None; }
+
+        let last_backup_at = last_backup_at.format("%Y-%m-%d %H:%M:%S");
+        if fingerprint.is_some() && fingerprint == last_fingerprint {
+            return Some(format!("world files unchanged since the last backup at {}", last_backup_at));
+        }
+        if !self.players_seen {
+            return Some(format!("no players online since the last backup at {}", last_backup_at));
+        }
+        None
+    }
+}
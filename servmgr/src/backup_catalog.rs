@@ -0,0 +1,163 @@
+use std::{fs, io, path::{Path, PathBuf}};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use crate::archive::{self, ArchiveEntry, ArchiveError, ArchiveFormat};
+use crate::{backup_repository, config};
+use crate::error::ServmgrError;
+
+pub const BACKUP_FILE_PREFIX: &str = "world_backup_";
+pub const BACKUP_TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+const BACKUP_TIMESTAMP_LENGTH: usize = "2026-01-01_00-00-00".len();
+const PIN_MARKER_EXTENSION: &str = ".pinned";
+
+/// How a backup is stored, as a self-contained archive or as a manifest in the incremental repository
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BackupStorage {
+    Archive(ArchiveFormat),
+    Repository,
+}
+
+impl BackupStorage {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BackupStorage::Archive(format) => format.extension(),
+            BackupStorage::Repository => backup_repository::MANIFEST_EXTENSION,
+        }
+    }
+}
+
+/// A backup found in the backups directory or its repository, the size of a repository backup is what it added
+/// to the repository when it was made
+pub struct BackupEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub created_at: DateTime<Local>,
+    pub size: u64,
+    pub pinned: bool,
+    pub storage: BackupStorage,
+    pub schedule: Option<String>,
+}
+
+impl BackupEntry {
+    /// Streams through the backup's files without extracting them, the callback returns false to stop early
+    pub fn for_each_entry(&self, callback: impl FnMut(ArchiveEntry) -> io::Result<bool>) -> io::Result<()> {
+        match self.storage {
+            BackupStorage::Archive(_) => archive::for_each_entry(&self.path, callback),
+            BackupStorage::Repository => backup_repository::for_each_entry(&self.path, callback),
+        }
+    }
+
+    pub fn extract(&self, destination: &Path) -> Result<(), ArchiveError> {
+        match self.storage {
+            BackupStorage::Archive(_) => archive::extract_archive(&self.path, destination),
+            BackupStorage::Repository => backup_repository::extract_backup(&self.path, destination),
+        }
+    }
+}
+
+pub fn backups_directory() -> PathBuf {
+    config::get().backup_directory.clone()
+}
+
+/// Pinned backups are marked by an empty `<backup>.pinned` file next to them
+pub fn pin_marker_path(backup_path: &Path) -> PathBuf {
+    let mut marker_path = backup_path.as_os_str().to_owned();
+    marker_path.push(PIN_MARKER_EXTENSION);
+    PathBuf::from(marker_path)
+}
+
+/// Backups taken by a schedule carry its name after the timestamp (e.g.) world_backup_2026-10-01_04-00-00_nightly.tar.zst
+pub fn backup_file_name(created_at: &DateTime<Local>, storage: BackupStorage, schedule: Option<&str>) -> String {
+    let schedule_suffix = schedule.map(|schedule| format!("_{}", schedule)).unwrap_or_default();
+    format!("{}{}{}{}", BACKUP_FILE_PREFIX, created_at.format(BACKUP_TIMESTAMP_FORMAT), schedule_suffix, storage.extension())
+}
+
+/// Where a new backup goes, archives sit in the backups directory and manifests in the repository inside it
+pub fn backup_path(created_at: &DateTime<Local>, storage: BackupStorage, schedule: Option<&str>) -> PathBuf {
+    let directory = match storage {
+        BackupStorage::Archive(_) => backups_directory(),
+        BackupStorage::Repository => backup_repository::manifests_directory(&backups_directory()),
+    };
+    directory.join(backup_file_name(created_at, storage, schedule))
+}
+
+/// Parses the creation time and schedule out of a `world_backup_%Y-%m-%d_%H-%M-%S[_<schedule>].<extension>` file name
+pub fn parse_backup_file_name(file_name: &str, storage: BackupStorage) -> Option<(DateTime<Local>, Option<String>)> {
+    let stem = file_name.strip_prefix(BACKUP_FILE_PREFIX)?.strip_suffix(storage.extension())?;
+    let (timestamp, schedule) = match stem.split_at_checked(BACKUP_TIMESTAMP_LENGTH)? {
+        (timestamp, "") => (timestamp, None),
+        (timestamp, suffix) => (timestamp, Some(suffix.strip_prefix('_').filter(|schedule| !schedule.is_empty())?)),
+    };
+    let naive_timestamp = NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT).ok()?;
+    Some((Local.from_local_datetime(&naive_timestamp).earliest()?, schedule.map(String::from)))
+}
+
+/// Every backup in the backups directory, oldest first
+pub fn list_backups() -> io::Result<Vec<BackupEntry>> {
+    list_backups_in(&backups_directory())
+}
+
+pub fn list_backups_in(directory: &Path) -> io::Result<Vec<BackupEntry>> {
+    let mut backups = read_backups(directory, |name| ArchiveFormat::from_file_name(name).map(BackupStorage::Archive))?;
+    backups.extend(read_backups(&backup_repository::manifests_directory(directory), |name| {
+        name.ends_with(backup_repository::MANIFEST_EXTENSION).then_some(BackupStorage::Repository)
+    })?);
+
+    backups.sort_by_key(|backup| backup.created_at);
+    Ok(backups)
+}
+
+fn read_backups(directory: &Path, storage_of: impl Fn(&str) -> Option<BackupStorage>) -> io::Result<Vec<BackupEntry>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    Ok(entries.filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let storage = storage_of(&name)?;
+            let (created_at, schedule) = parse_backup_file_name(&name, storage)?;
+            let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+            let size = match storage {
+                BackupStorage::Archive(_) => metadata.len(),
+                BackupStorage::Repository => backup_repository::read_stored_size(&entry.path()).unwrap_or(metadata.len()),
+            };
+            let pinned = pin_marker_path(&entry.path()).exists();
+            Some(BackupEntry { name, path: entry.path(), created_at, size, pinned, storage, schedule })
+        })
+        .collect())
+}
+
+/// Finds a backup by its file name (with or without the extension) or `latest`
+pub fn resolve_backup(selector: &str) -> Result<BackupEntry, ServmgrError> {
+    let backups = list_backups().map_err(ServmgrError::filesystem("read", &backups_directory()))?;
+
+    let found = if selector == "latest" {
+        backups.into_iter().next_back()
+    } else {
+        backups.into_iter().find(|backup| backup.name == selector || backup.name.strip_suffix(backup.storage.extension()) == Some(selector))
+    };
+
+    found.ok_or_else(|| ServmgrError::Invalid(format!("No backup matching '{}' in {}", selector, backups_directory().display())))
+}
+
+/// Finds the newest backup taken at or before a point in time
+pub fn resolve_backup_at(point_in_time: &DateTime<Local>) -> Result<BackupEntry, ServmgrError> {
+    let backups = list_backups().map_err(ServmgrError::filesystem("read", &backups_directory()))?;
+
+    backups.into_iter()
+        .rfind(|backup| backup.created_at <= *point_in_time)
+        .ok_or_else(|| ServmgrError::Invalid(format!("No backup was taken at or before {}", point_in_time.format("%Y-%m-%d %H:%M:%S"))))
+}
+
+/// Parses a user supplied local timestamp such as "2026-10-01 04:00", "2026-10-01_04-00-00" or "2026-10-01"
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Local>> {
+    const FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", BACKUP_TIMESTAMP_FORMAT];
+
+    let naive_timestamp = FORMATS.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(timestamp.trim(), format).ok())
+        .or_else(|| NaiveDate::parse_from_str(timestamp.trim(), "%Y-%m-%d").ok()?.and_hms_opt(23, 59, 59))?;
+
+    Local.from_local_datetime(&naive_timestamp).earliest()
+}
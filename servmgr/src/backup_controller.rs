@@ -1,347 +1,214 @@
-use std::{thread, path::PathBuf, fs, process::Command, time::Duration};
+use std::{path::PathBuf, fs, time::{Duration, Instant}};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::os::unix::process::CommandExt;
 use console::style;
-use chrono;
-
-fn is_rcon_responding(rcon_password: &str) -> bool {
-    let rcon_connection_test_cmd_output = Command::new("rcon-cli")
-        .arg("--password")
-        .arg(rcon_password)
-        .arg("list")
-        .output()
-        .expect("Failed to execute rcon connection test command");
-
-    rcon_connection_test_cmd_output.status.success()
+use crate::rcon::{self, RconClient};
+use crate::archive;
+use crate::backup_catalog::{self, BackupStorage};
+use crate::backup_manifest::{self, BackupManifest};
+use crate::{backup_repository, backup_schedule, backup_state, config, disk_usage, server_log::{self, LogWatcher}, world_manager};
+use crate::backup_notifications::{BackupEvent, BackupNotifier, NotificationConfig, Verbosity};
+use crate::save_guard::SaveGuard;
+use crate::error::ServmgrError;
+
+/// What the server answers and logs once `save-all` has written everything to disk, and when it couldn't
+const SAVE_COMPLETE_MESSAGE: &str = "Saved the game";
+const SAVE_FAILED_MESSAGE: &str = "Unable to save the game";
+
+pub const DEFAULT_SAVE_TIMEOUT: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Notifications are best effort, a backup carries on when players couldn't be told about it
+fn send_backup_notification(rcon: &mut RconClient, status_spinner: &ProgressBar, notifier: &BackupNotifier, event: BackupEvent) {
+    if let Err(err) = notifier.notify(rcon, event) {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to send RCON server notification").cyan(), err));
+    }
 }
 
-pub fn backup_minecraft_server(rcon_password: &str) -> bool {
+pub fn new_status_spinner(message: &str) -> ProgressBar {
     let status_spinner = ProgressBar::new_spinner();
     status_spinner.set_style(
         ProgressStyle::default_spinner()
             .tick_strings(&["(●     )", "( ●    )", "(  ●   )", "(   ●  )", "(    ● )", "(     ●)", "(    ● )", "(   ●  )", "(  ●   )", "( ●    )"])
             .template("{spinner:.cyan} [{elapsed}] {msg}")
             .unwrap());
-    status_spinner.set_message(style("Backing up Minecraft server...").cyan().to_string());
+    status_spinner.set_message(style(message).cyan().to_string());
     status_spinner.enable_steady_tick(Duration::from_millis(100));
+    status_spinner
+}
 
-    if is_rcon_responding(rcon_password) { // check rcon connection
-        status_spinner.println(
-            format!("{} {}", style("●").green().to_string(), style("RCON Interface: RESPONDING").cyan().to_string()));
-
-        // starting backup notifications
-        let rcon_server_backup_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"Creating Server Backup...\",color:\"#FF6600\"}")
-            .output()
-            .expect("Failed to execute rcon server backup notification");
-
-        if !rcon_server_backup_notification_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server backup notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        } 
-
-        // disabling auto-writing from RAM to DISK
-        let rcon_server_save_off_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("save-off")
-            .output()
-            .expect("Failed to execute rcon server save-off command");
-
-        if !rcon_server_save_off_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to disable auto-writing from RAM to DISK").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        status_spinner.println(style(format!("=> Disabled auto-writing from RAM to DISK")).dim().to_string());
-        let rcon_server_save_off_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"=> Disabled auto-writing from RAM to DISK\",color:\"#A094FF\"}")
-            .output()
-            .expect("Failed to execute rcon server save-off notification");
-
-        if !rcon_server_save_off_notification_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server save-off notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        // flush server DISK to RAM
-        status_spinner.println(style(format!("=> Flushing DISK to RAM...")).dim().to_string());
-        let rcon_server_flush_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"=> Flushing DISK to RAM...\",color:\"#A094FF\"}")
-            .output()
-            .expect("Failed to execute rcon server flush notification");
-
-        if !rcon_server_flush_notification_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server flush notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        let rcon_server_flush_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("save-all")
-            .output()
-            .expect("Failed to execute rcon server flush command");
-
-        if !rcon_server_flush_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to flush server DISK to RAM").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        thread::sleep(Duration::from_secs(10)); // give ample time to let server write from RAM to DISK
-
-        status_spinner.println(style(format!("=> => Flushed DISK to RAM")).dim().to_string());
-        let rcon_server_flush_finished_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"=> => Flushed DISK to RAM\",color:\"#A094FF\"}")
-            .output()
-            .expect("Failed to execute rcon server flush finished notification");
-
-        if !rcon_server_flush_finished_notification_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server flush finished notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        // save and compress world folder
-        fs::create_dir_all("/home/mcadmin/minecraft_server/backups").expect("Failed to create backups directory");
-        let current_time = chrono::Local::now();
-
-        status_spinner.println(style(format!("=> Compressing world folder...")).dim().to_string());
-        let rcon_server_compress_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"=> Compressing world folder...\",color:\"#A094FF\"}")
-            .output()
-            .expect("Failed to execute rcon server compress notification");
-
-        if !rcon_server_compress_notification_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server compress notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        let backup_save_directory: PathBuf = PathBuf::from("/home/mcadmin/minecraft_server/backups");
-        let backup_file_name = format!("world_backup_{}.tar.gz", current_time.format("%Y-%m-%d_%H-%M-%S"));
-        let backup_file_path = backup_save_directory.join(&backup_file_name);
-        let world_path: PathBuf = PathBuf::from("/home/mcadmin/minecraft_server/world");
-        let tar_command_output = Command::new("tar")
-            .arg("-czf")
-            .arg(backup_file_path.to_str().unwrap())
-            .arg(world_path.to_str().unwrap())
-            .output()
-            .expect("Failed to execute tar command");
-
-        if !tar_command_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to compress world folder").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        status_spinner.println(style(format!("=> => World folder compressed and saved")).dim().to_string());
-        let rcon_server_compress_finished_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"=> => World folder compressed and saved\",color:\"#A094FF\"}")
-            .output()
-            .expect("Failed to execute rcon server compress finished notification");
+/// How a backup is written and the schedule that took it, if any
+pub struct BackupOptions<'a> {
+    pub storage: BackupStorage,
+    pub level: Option<u32>,
+    pub schedule: Option<&'a str>,
+    /// How long the server may take to save the world before the backup is abandoned
+    pub save_timeout: chrono::Duration,
+    /// Overrides the verbosity of the notifications file
+    pub notifications: Option<Verbosity>,
+}
 
-        if !rcon_server_compress_finished_notification_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server compress finished notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
+/// The backup a successful run wrote
+pub struct BackupOutcome {
+    pub name: String,
+    pub size: u64, // on disk, only the newly stored chunks for repository backups
+}
 
-        // enable auto-writing from RAM to DISK
-        let rcon_server_save_on_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("save-on")
-            .output()
-            .expect("Failed to execute rcon server save-on command");
+/// Backs up the world and records the attempt in the backup state file, whether it succeeded or not
+pub fn backup_minecraft_server(rcon_password: &str, options: &BackupOptions) -> Result<BackupOutcome, ServmgrError> {
+    let started_at = chrono::Local::now();
+    let result = take_backup(rcon_password, options);
+    if let Err(err) = backup_state::record_attempt(started_at, options.schedule, &result) {
+        eprintln!("{}: Failed to write {} ({})", style("[ERROR]").red(), backup_state::backup_state_path().display(), err);
+    }
+    result
+}
 
-        if !rcon_server_save_on_output.status.success() {
-            status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to enable auto-writing from RAM to DISK").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
+fn take_backup(rcon_password: &str, options: &BackupOptions) -> Result<BackupOutcome, ServmgrError> {
+    // checked before touching the server so a bad level never leaves saving turned off
+    if let BackupStorage::Archive(format) = options.storage {
+        format.check_level(options.level).map_err(|err| ServmgrError::Invalid(err.to_string()))?;
+    }
+    let mut notification_config = NotificationConfig::load(&config::get().notifications_path)?;
+    if let Some(verbosity) = options.notifications { notification_config.verbosity = verbosity; }
+    let mut notifier = BackupNotifier::new(notification_config, options.schedule);
 
-        status_spinner.println(style(format!("=> Enabled auto-writing from RAM to DISK")).dim().to_string());
-        let rcon_server_save_on_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg("execute as @a run tellraw @p {text:\"=> Enabled auto-writing from RAM to DISK\",color:\"#A094FF\"}")
-            .output()
-            .expect("Failed to execute rcon server save-on notification");
+    let status_spinner = new_status_spinner("Backing up Minecraft server...");
 
-        if !rcon_server_save_on_notification_output.status.success() {
+    let result = match rcon::open_responding_session(Some(rcon_password)) { // check rcon connection
+        Ok(rcon) => {
             status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server save-on notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-
-        // notify that backup is complete
-        status_spinner.println(style(format!("Backup Complete: {}", backup_file_path.to_str().unwrap())).dim().to_string());
-        let rcon_server_backup_complete_notification_output = Command::new("rcon-cli")
-            .arg("--password")
-            .arg(rcon_password)
-            .arg(format!("execute as @a run tellraw @p {{\"text\":\"Backup complete: {}\",\"color\":\"dark_green\"}}", backup_file_name.to_string()))
-            .output()
-            .expect("Failed to execute rcon server backup complete notification");
-
-        if !rcon_server_backup_complete_notification_output.status.success() {
+                format!("{} {}", style("●").green(), style("RCON Interface: RESPONDING").cyan()));
+            let result = run_backup_steps(rcon, rcon_password, &status_spinner, options, &mut notifier);
+            if let Err(err) = &result {
+                // the session the steps used may be what broke
+                notifier.set("error", &err.summary());
+                if let Ok(mut rcon) = rcon::open_responding_session(Some(rcon_password)) {
+                    send_backup_notification(&mut rcon, &status_spinner, &notifier, BackupEvent::Failed);
+                }
+            }
+            result
+        },
+        Err(err) => {
             status_spinner.println(
-                format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server backup complete notification").cyan().to_string()));
-            status_spinner.finish_and_clear();
-            return false;
-        }
-    } else {
-        status_spinner.println(
-            format!("{} {}", style("○").red().to_string(), style("RCON Interface: NOT RESPONDING (Server may be booting...)").cyan().to_string()));
-        status_spinner.finish_and_clear();
-        return false;
-    }
+                format!("{} {} ({})", style("○").red(), style("RCON Interface: NOT RESPONDING (Server may be booting...)").cyan(), err));
+            Err(ServmgrError::rcon(err))
+        },
+    };
 
     status_spinner.finish_and_clear();
-    true
+    result
 }
 
-pub fn start_schedule_minecraft_server_backups(rcon_password: &str, interval: &Duration, detached: &bool) {
-    let session_name = "backup_server_mgr";
-
-    // check if tmux session already exists
-    let tmux_has_session_cmd_output = Command::new("tmux")
-        .arg("has-session")
-        .arg("-t")
-        .arg(session_name)
-        .output()
-        .expect("Failed to check if tmux session exists");
-
-    if tmux_has_session_cmd_output.status.success() {
-        println!("{}: Tmux session '{}' already exists", style("[INFO]").cyan(), session_name);
-        return;
-    }
-
-    // create tmux session
-    let tmux_session_creation_cmd_output = Command::new("tmux")
-        .arg("new-session")
-        .arg("-d")
-        .arg("-s")
-        .arg(session_name)
-        .output()
-        .expect("Failed to create tmux session");
-
-    if !tmux_session_creation_cmd_output.status.success() {
-        eprintln!("{} Failed to create tmux session with status code: {:?}", style("[ERROR]").red(), tmux_session_creation_cmd_output.status.code().unwrap_or(-1));
-        return;
-    }
-
-    // start scheduled backups in tmux session
-    let start_scheduled_backups_cmd_output = Command::new("tmux")
-        .arg("send-keys")
-        .arg("-t")
-        .arg(session_name)
-        .arg(format!("/home/mcadmin/minecraft_server/start_scheduled_backups.sh -s {} -p {}", interval.as_secs(), rcon_password))
-        .arg("C-m")
-        .output()
-        .expect("Failed to start scheduled backups");
-
-    if !start_scheduled_backups_cmd_output.status.success() {
-        eprintln!("{}: Failed to start scheduled backups with status code: {:?}", style("[ERROR]").red(), start_scheduled_backups_cmd_output.status.code().unwrap_or(-1));
-        return;
-    }
-
-    // attach to tmux session if detached is false
-    let mut attach_session_cmd: Command = Command::new("tmux");
-    attach_session_cmd.arg("attach-session")
-        .arg("-t")
-        .arg(session_name);
-
-    println!("{}", style("Starting Scheduled Backups...").cyan());
-    if !*detached { 
-        let err = attach_session_cmd.exec();
-        eprintln!("{}: {}", style("Failed to start scheduled backups").red(), err);
+/// Has the server write every chunk to disk with `save-all flush` and waits until it has, which the command's response
+/// usually says already, otherwise the server log does once the save finishes
+fn flush_world(rcon: &mut RconClient, timeout: chrono::Duration) -> Result<(), ServmgrError> {
+    let log_path = server_log::latest_log_path();
+    let mut server_log = LogWatcher::open(&log_path); // opened first so a save that finishes before the response is still seen
+    let response = rcon.command("save-all flush").map_err(ServmgrError::rcon_command("save-all flush"))?;
+
+    let saved_line = if response.contains(SAVE_COMPLETE_MESSAGE) || response.contains(SAVE_FAILED_MESSAGE) { Some(response) } else {
+        server_log.wait_for_line(|line| line.contains(SAVE_COMPLETE_MESSAGE) || line.contains(SAVE_FAILED_MESSAGE),
+            timeout.to_std().unwrap_or_default()).map_err(ServmgrError::filesystem("read", &log_path))?
+    };
+
+    match saved_line {
+        Some(line) if line.contains(SAVE_FAILED_MESSAGE) => Err(ServmgrError::Failed(String::from("The server failed to save the world (is the disk full?)"))),
+        Some(_) => Ok(()),
+        None => Err(ServmgrError::Failed(format!("The server didn't finish saving the world within {}", backup_schedule::format_duration(timeout)))),
     }
 }
 
-pub fn stop_scheduled_minecraft_server_backups(rcon_password: &str) {
-    println!("{}", style("[INFO] Stopping automatic backup schedule...").cyan().to_string());
-    let session_name = "backup_server_mgr";
-
-    // check if tmux session exists
-    let tmux_has_session_cmd_output = Command::new("tmux")
-        .arg("has-session")
-        .arg("-t")
-        .arg(session_name)
-        .output()
-        .expect("Failed to check if tmux session exists");
-
-    if !tmux_has_session_cmd_output.status.success() {
-        println!("{}: Tmux session '{}' does not exist", style("[INFO]").cyan(), session_name);
-        return;
-    }
-
-    // enable auto-writing from RAM to DISK
-    let rcon_server_save_on_output = Command::new("rcon-cli")
-        .arg("--password")
-        .arg(rcon_password)
-        .arg("save-on")
-        .output()
-        .expect("Failed to execute rcon server save-on command");
-
-    if !rcon_server_save_on_output.status.success() {
-        println!("{}", format!("{} {}", style("○").red().to_string(), style("Failed to enable auto-writing from RAM to DISK").cyan().to_string()));
-        return;
-    }
-
-    println!("{}", style(format!("=> Enabled auto-writing from RAM to DISK")).dim().to_string());
-    let rcon_server_save_on_notification_output = Command::new("rcon-cli")
-        .arg("--password")
-        .arg(rcon_password)
-        .arg("execute as @a run tellraw @p {text:\"=> Enabled auto-writing from RAM to DISK\",color:\"#A094FF\"}")
-        .output()
-        .expect("Failed to execute rcon server save-on notification");
+fn run_backup_steps(mut rcon: RconClient, rcon_password: &str, status_spinner: &ProgressBar, options: &BackupOptions, notifier: &mut BackupNotifier)
+    -> Result<BackupOutcome, ServmgrError> {
+    // starting backup notifications
+    send_backup_notification(&mut rcon, status_spinner, notifier, BackupEvent::Started);
+
+    // disabling auto-writing from RAM to DISK, every return from here on turns it back on when the guard is dropped
+    let mut save_guard = SaveGuard::disable(rcon, rcon_password).inspect_err(|err| {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to disable auto-writing from RAM to DISK").cyan(), err.summary()));
+    })?;
+    let rcon: &mut RconClient = &mut save_guard;
+    status_spinner.println(style("=> Disabled auto-writing from RAM to DISK").dim().to_string());
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::SavingDisabled);
+
+    // flush server DISK to RAM
+    status_spinner.println(style("=> Flushing DISK to RAM...").dim().to_string());
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::Flushing);
+    let flush_started_at = Instant::now();
+    flush_world(rcon, options.save_timeout).inspect_err(|err| {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to flush server DISK to RAM").cyan(), err.summary()));
+    })?;
+
+    status_spinner.println(style(format!("=> => Flushed DISK to RAM ({:.1}s)", flush_started_at.elapsed().as_secs_f64())).dim().to_string());
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::Flushed);
+
+    // save and compress world folder
+    let backups_directory = backup_catalog::backups_directory();
+    fs::create_dir_all(&backups_directory).map_err(ServmgrError::filesystem("create", &backups_directory))?;
+    let current_time = chrono::Local::now();
+
+    status_spinner.println(style("=> Compressing world folder...").dim().to_string());
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::Compressing);
+
+    let storage = options.storage;
+    let backup_file_name = backup_catalog::backup_file_name(&current_time, storage, options.schedule);
+    let backup_file_path = backup_catalog::backup_path(&current_time, storage, options.schedule);
+    let world_path: PathBuf = world_manager::active_world_path();
+    let world_directory_name = PathBuf::from(world_path.file_name().unwrap_or_default());
+
+    // paths inside the backup are relative to the world folder's parent (e.g.) world/level.dat
+    let show_progress = |progress: &archive::ArchiveProgress| {
+        status_spinner.set_message(style(format!("Backing up Minecraft server... {} files, {} ({})",
+            progress.files_done, disk_usage::format_size(progress.bytes_done), progress.current_file.display())).cyan().to_string());
+    };
+    let backup_result = match storage {
+        BackupStorage::Archive(format) => archive::write_archive(&world_path, &world_directory_name, &backup_file_path, format, options.level, show_progress)
+            .map(|summary| (format!("Archived {} files ({})", summary.files, disk_usage::format_size(summary.bytes)), summary,
+                fs::metadata(&backup_file_path).map(|metadata| metadata.len()).unwrap_or_default())),
+        BackupStorage::Repository => backup_repository::write_backup(&world_path, &world_directory_name, &backup_file_path, show_progress)
+            .map(|summary| (format!("Backed up {} files ({}), {} new in the repository",
+                summary.archive.files, disk_usage::format_size(summary.archive.bytes), disk_usage::format_size(summary.stored_bytes)),
+                summary.archive, summary.stored_bytes)),
+    };
+    status_spinner.set_message(style("Backing up Minecraft server...").cyan().to_string());
 
-    if !rcon_server_save_on_notification_output.status.success() {
-        println!("{}", format!("{} {}", style("○").red().to_string(), style("Failed to send RCON server save-on notification").cyan().to_string()));
-        return;
+    let (summary, size) = match backup_result {
+        Ok((message, summary, size)) => {
+            status_spinner.println(style(format!("=> => {}", message)).dim().to_string());
+            (summary, size)
+        },
+        Err(err) => {
+            status_spinner.println(
+                format!("{} {} ({})", style("○").red(), style("Failed to compress world folder").cyan(), err));
+            let _ = fs::remove_file(&backup_file_path); // never leave a partial archive behind for restore or pruning to pick up
+            return Err(ServmgrError::archive("compress the world folder")(err));
+        },
+    };
+
+    // checksums of everything that went in, for `backup verify`
+    let level_metadata = fs::File::open(world_path.join("level.dat")).and_then(world_manager::read_level_metadata).ok();
+    let manifest = BackupManifest::new(summary, level_metadata.as_ref());
+    if let Err(err) = backup_manifest::write_manifest(&backup_file_path, storage, &manifest) {
+        status_spinner.println(
+            format!("{} {} ({})", style("○").red(), style("Failed to write the backup manifest").cyan(), err));
+        return Err(ServmgrError::filesystem("write the manifest of", &backup_file_path)(err));
     }
 
-    // kill tmux session
-    let tmux_kill_session_cmd_output = Command::new("tmux")
-        .arg("kill-session")
-        .arg("-t")
-        .arg(session_name)
-        .output()
-        .expect("Failed to kill tmux session");
-
-    if !tmux_kill_session_cmd_output.status.success() {
-        eprintln!("{} Failed to stop tmux session with status code: {:?}", style("[ERROR]").red(), tmux_kill_session_cmd_output.status.code().unwrap_or(-1));
-        return;
-    }
+    status_spinner.println(style("=> => World folder compressed and saved").dim().to_string());
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::Compressed);
 
-    println!("{}", style("[INFO] Automatic backup schedule stopped").cyan().to_string());
+    // enable auto-writing from RAM to DISK
+    let mut rcon = save_guard.restore().inspect_err(|err| {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to enable auto-writing from RAM to DISK").cyan(), err.summary()));
+    })?;
+    let rcon = &mut rcon;
+    status_spinner.println(style("=> Enabled auto-writing from RAM to DISK").dim().to_string());
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::SavingEnabled);
+
+    // notify that backup is complete
+    status_spinner.println(style(format!("Backup Complete: {}", backup_file_path.display())).dim().to_string());
+    notifier.set("file", &backup_file_name);
+    notifier.set("size", &disk_usage::format_size(size));
+    send_backup_notification(rcon, status_spinner, notifier, BackupEvent::Finished);
+
+    Ok(BackupOutcome { name: backup_file_name, size })
 }
@@ -0,0 +1,381 @@
+use std::{
+    fs, io::{self, Read, Write}, os::unix::{net::{UnixListener, UnixStream}, process::CommandExt},
+    path::PathBuf, process::{Command, Stdio}, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, thread, time::{Duration, Instant},
+};
+use chrono::{DateTime, Local};
+use console::style;
+use serde_json::{json, Value};
+use crate::backup_activity::{self, ActivityTracker, PlayerWatcher};
+use crate::backup_notifications::{BackupEvent, BackupNotifier, NotificationConfig};
+use crate::error::ServmgrError;
+use crate::rcon_password::{RconPassword, RCON_PASSWORD_VARIABLE};
+use crate::{backup_controller, backup_retention, backup_state, backup_schedule::{self, NamedSchedule}, config, rcon, world_manager};
+
+const PID_FILE_NAME: &str = "servmgr_backup_daemon.pid";
+const SOCKET_FILE_NAME: &str = "servmgr_backup_daemon.sock";
+const LOG_FILE_NAME: &str = "servmgr_backup_daemon.log";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How often the daemon checks for online players, players who join and leave in between are picked up from the log
+const PLAYER_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What the daemon reports over its status socket
+struct DaemonStatus {
+    started_at: DateTime<Local>,
+    schedules: Vec<(String, String, Option<DateTime<Local>>)>, // name, spec and next run
+    backing_up: Option<String>,
+    stopping: bool,
+    backups_taken: u64,
+    backups_failed: u64,
+    backups_skipped: u64,
+    last_backup: Option<(DateTime<Local>, String, bool)>,
+    last_skip: Option<(DateTime<Local>, String, String)>, // when, schedule and reason
+}
+
+impl DaemonStatus {
+    fn to_json(&self) -> Value {
+        json!({
+            "pid": std::process::id(),
+            "started_at": self.started_at.to_rfc3339(),
+            "schedules": self.schedules.iter()
+                .map(|(name, when, next_run_at)| json!({"name": name, "when": when, "next_run_at": next_run_at.map(|at| at.to_rfc3339())}))
+                .collect::<Vec<_>>(),
+            "backing_up": self.backing_up,
+            "stopping": self.stopping,
+            "backups_taken": self.backups_taken,
+            "backups_failed": self.backups_failed,
+            "backups_skipped": self.backups_skipped,
+            "last_backup": self.last_backup.as_ref()
+                .map(|(at, schedule, success)| json!({"at": at.to_rfc3339(), "schedule": schedule, "success": success})),
+            "last_skip": self.last_skip.as_ref()
+                .map(|(at, schedule, reason)| json!({"at": at.to_rfc3339(), "schedule": schedule, "reason": reason})),
+            "next_backup_at": self.schedules.iter().filter_map(|(_, _, next_run_at)| *next_run_at).min().map(|at| at.to_rfc3339()),
+        })
+    }
+
+    /// Writes the soonest scheduled run to the backup state file for `backup status`
+    fn publish_next_run(&self) {
+        let next_run = self.schedules.iter()
+            .filter_map(|(name, _, next_run_at)| next_run_at.map(|at| (at, name.as_str())))
+            .min_by_key(|(at, _)| *at);
+        if let Err(err) = backup_state::record_next_run(next_run) {
+            eprintln!("{}: Failed to write {} ({})", style("[ERROR]").red(), backup_state::backup_state_path().display(), err);
+        }
+    }
+}
+
+fn daemon_file_path(file_name: &str) -> PathBuf {
+    config::get().server_directory.join(file_name)
+}
+
+/// Opens the PID file and locks it, the lock is held for as long as the daemon runs so a crashed daemon never
+/// leaves a stale lock behind, returns `None` if another daemon holds it
+fn lock_pid_file() -> io::Result<Option<fs::File>> {
+    let pid_file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(daemon_file_path(PID_FILE_NAME))?;
+    try_lock_file(pid_file)
+}
+
+fn try_lock_file(pid_file: fs::File) -> io::Result<Option<fs::File>> {
+    match pid_file.try_lock() {
+        Ok(()) => Ok(Some(pid_file)),
+        Err(fs::TryLockError::WouldBlock) => Ok(None),
+        Err(fs::TryLockError::Error(err)) => Err(err),
+    }
+}
+
+/// The PID of the running daemon, `None` if no daemon holds the lock
+pub fn running_daemon_pid() -> io::Result<Option<i32>> {
+    let mut pid_file = match fs::File::open(daemon_file_path(PID_FILE_NAME)) {
+        Ok(pid_file) => pid_file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut pid = String::new();
+    pid_file.read_to_string(&mut pid)?;
+    if try_lock_file(pid_file)?.is_some() { return Ok(None); } // the lock is released again when the file is dropped
+
+    pid.trim().parse().map(Some).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "The backup daemon's PID file is malformed"))
+}
+
+/// Answers every connection to the status socket with the daemon's status as a line of JSON
+fn serve_status(listener: UnixListener, status: Arc<Mutex<DaemonStatus>>) {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue; };
+        let status_json = status.lock().unwrap().to_json();
+        let _ = writeln!(stream, "{}", status_json);
+    }
+}
+
+/// The ad hoc schedule given with `--when`, or every schedule in the schedules file
+fn load_daemon_schedules(when: Option<&str>) -> Result<Vec<NamedSchedule>, ServmgrError> {
+    match when {
+        Some(when) => backup_schedule::ad_hoc_schedule(when).map(|schedule| vec![schedule]),
+        None => backup_schedule::load_schedules(&config::get().schedules_path),
+    }
+}
+
+/// Runs scheduled backups until SIGINT or SIGTERM, a signal that arrives mid-backup lets the backup finish first
+pub fn run_backup_daemon(rcon_password: &str, when: Option<&str>) -> Result<(), ServmgrError> {
+    let schedules = load_daemon_schedules(when)?;
+
+    let pid_path = daemon_file_path(PID_FILE_NAME);
+    let mut pid_file = lock_pid_file().map_err(ServmgrError::filesystem("lock", &pid_path))?
+        .ok_or_else(|| ServmgrError::Invalid(String::from("The backup daemon is already running")))?;
+    pid_file.set_len(0).and_then(|_| write!(pid_file, "{}", std::process::id())).map_err(ServmgrError::filesystem("write", &pid_path))?;
+
+    let terminate_requested = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&terminate_requested))
+            .map_err(|err| ServmgrError::Failed(format!("Failed to install the signal handler ({})", err)))?;
+    }
+
+    // the socket can only be stale here since the PID file lock is ours
+    let socket_path = daemon_file_path(SOCKET_FILE_NAME);
+    let _ = fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path).map_err(ServmgrError::filesystem("open the status socket", &socket_path))?;
+
+    let started_at = Local::now();
+    let status = Arc::new(Mutex::new(DaemonStatus {
+        started_at,
+        schedules: schedules.iter()
+            .map(|schedule| (schedule.name.clone(), schedule.when.clone(), schedule.schedule.next_run(started_at)))
+            .collect(),
+        backing_up: None, stopping: false, backups_taken: 0, backups_failed: 0, backups_skipped: 0, last_backup: None, last_skip: None,
+    }));
+    status.lock().unwrap().publish_next_run();
+    let socket_status = Arc::clone(&status);
+    thread::spawn(move || serve_status(listener, socket_status));
+
+    println!("{}: Backup daemon started (pid {})", style("[INFO]").cyan(), std::process::id());
+    for schedule in &schedules {
+        println!("{}", style(format!("=> Schedule '{}': {}", schedule.name, schedule.when)).dim());
+    }
+
+    let mut activity: Vec<ActivityTracker> = schedules.iter().map(|_| ActivityTracker::new()).collect();
+    let mut player_watcher = PlayerWatcher::new(rcon_password);
+    let mut last_player_poll: Option<Instant> = None;
+    let watch_players = schedules.iter().any(|schedule| schedule.skip_when_idle);
+
+    while !terminate_requested.load(Ordering::Relaxed) {
+        if watch_players && last_player_poll.is_none_or(|last_player_poll| last_player_poll.elapsed() >= PLAYER_POLL_INTERVAL) {
+            let player_count = player_watcher.online_player_count();
+            for tracker in activity.iter_mut() { tracker.observe_players(player_count); }
+            last_player_poll = Some(Instant::now());
+        }
+
+        let now = Local::now();
+        let due_schedule = status.lock().unwrap().schedules.iter()
+            .position(|(_, _, next_run_at)| next_run_at.is_some_and(|next_run_at| next_run_at <= now));
+        let Some(index) = due_schedule else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        let schedule = &schedules[index];
+
+        if schedule.skip_when_idle {
+            // every schedule gets the poll, the joins it read from the log aren't read again
+            let player_count = player_watcher.online_player_count();
+            for tracker in activity.iter_mut() { tracker.observe_players(player_count); }
+            let fingerprint = backup_activity::world_fingerprint(&world_manager::active_world_path()).ok();
+            if let Some(reason) = activity[index].skip_reason(now, schedule.max_interval, fingerprint) {
+                println!("{}: Skipped the {} backup at {} ({})", style("[INFO]").cyan(), schedule.name, now.format("%Y-%m-%d %H:%M:%S"), reason);
+                let mut status = status.lock().unwrap();
+                status.backups_skipped += 1;
+                status.last_skip = Some((now, schedule.name.clone(), reason));
+                status.schedules[index].2 = schedule.schedule.next_run(now);
+                status.publish_next_run();
+                continue;
+            }
+        }
+
+        {
+            let mut status = status.lock().unwrap();
+            status.backing_up = Some(schedule.name.clone());
+            status.schedules[index].2 = None;
+        }
+        let backup_time = Local::now();
+        let options = backup_controller::BackupOptions {
+            storage: schedule.storage, level: schedule.level, schedule: Some(&schedule.name), save_timeout: schedule.save_timeout,
+            notifications: schedule.notifications,
+        };
+        let result = backup_controller::backup_minecraft_server(rcon_password, &options);
+        let success = result.is_ok();
+        if success {
+            println!("{}: Backed up Minecraft server at {} ({})", style("[INFO]").cyan(), backup_time.format("%Y-%m-%d %H:%M:%S"), schedule.name);
+            // taken after the backup's save so the next run compares against the world as it was backed up
+            activity[index].record_backup(backup_time, backup_activity::world_fingerprint(&world_manager::active_world_path()).ok());
+            if schedule.prune
+                && let Err(err) = backup_retention::prune_backups(&schedule.retention, backup_retention::PruneScope::Schedule(&schedule.name), false) {
+                err.report(false);
+            }
+        } else if let Err(err) = result {
+            eprintln!("{}: Failed to back up Minecraft server at {} ({}): {}",
+                style("[ERROR]").red(), backup_time.format("%Y-%m-%d %H:%M:%S"), schedule.name, err.summary());
+        }
+
+        // runs missed while backing up are skipped rather than run back to back
+        let mut status = status.lock().unwrap();
+        status.backing_up = None;
+        if success { status.backups_taken += 1; } else { status.backups_failed += 1; }
+        status.last_backup = Some((backup_time, schedule.name.clone(), success));
+        status.schedules[index].2 = schedule.schedule.next_run(Local::now());
+        status.publish_next_run();
+    }
+
+    {
+        let mut status = status.lock().unwrap();
+        status.stopping = true;
+        for (_, _, next_run_at) in status.schedules.iter_mut() { *next_run_at = None; }
+        status.publish_next_run();
+    }
+    println!("{}", style("Termination requested, disabling server backups...").cyan());
+    shut_down_backups(rcon_password);
+
+    let _ = fs::remove_file(&socket_path);
+    let _ = fs::remove_file(daemon_file_path(PID_FILE_NAME));
+    println!("{}", style("[INFO] Backup daemon stopped").cyan());
+    Ok(())
+}
+
+/// Makes sure the server is left saving to disk, whatever state the last backup left it in
+fn shut_down_backups(rcon_password: &str) {
+    let mut rcon = match rcon::open_responding_session(Some(rcon_password)) {
+        Ok(rcon) => rcon,
+        Err(err) => {
+            println!("{} {} ({})", style("○").red(), style("Failed to connect to the RCON interface").cyan(), err);
+            return;
+        },
+    };
+
+    if let Err(err) = rcon.command("save-on") {
+        println!("{} {} ({})", style("○").red(), style("Failed to enable auto-writing from RAM to DISK").cyan(), err);
+        return;
+    }
+    println!("{}", style("=> Enabled auto-writing from RAM to DISK").dim());
+
+    match NotificationConfig::load(&config::get().notifications_path) {
+        Ok(config) => { let _ = BackupNotifier::new(config, None).notify(&mut rcon, BackupEvent::BackupsDisabled); },
+        Err(err) => err.report(false),
+    }
+}
+
+/// Starts the backup daemon, in the foreground or detached from the terminal with its output going to a log file
+pub fn start_backup_daemon(rcon_password: &RconPassword, when: Option<&str>, detached: bool) -> Result<(), ServmgrError> {
+    if !detached { return run_backup_daemon(&rcon_password.password, when); }
+
+    // checked here so a bad schedule is reported to the terminal rather than the log file
+    load_daemon_schedules(when)?;
+
+    let pid_path = daemon_file_path(PID_FILE_NAME);
+    if let Some(pid) = running_daemon_pid().map_err(ServmgrError::filesystem("read", &pid_path))? {
+        println!("{}: The backup daemon is already running (pid {})", style("[INFO]").cyan(), pid);
+        return Ok(());
+    }
+
+    let log_path = daemon_file_path(LOG_FILE_NAME);
+    let spawn_result = std::env::current_exe()
+        .and_then(|executable| {
+            let log_file = fs::OpenOptions::new().create(true).append(true).open(&log_path)?;
+            let mut command = Command::new(executable);
+            // the daemon reads the same config file, settings from the environment are inherited anyway
+            if config::get().path.exists() { command.env("SERVMGR_CONFIG", &config::get().path); }
+            // never on the command line, where every user can read it, the daemon finds it again unless it was given
+            // with -p or typed in
+            if !rcon_password.source.is_findable() { command.env(RCON_PASSWORD_VARIABLE, &rcon_password.password); }
+            command
+                .args(["backup", "daemon"])
+                .args(when.map(|when| ["--when", when]).into_iter().flatten())
+                .stdin(Stdio::null())
+                .stdout(log_file.try_clone()?)
+                .stderr(log_file)
+                .process_group(0) // keeps the daemon alive when the terminal that started it closes
+                .spawn()
+        });
+
+    let child = spawn_result.map_err(ServmgrError::process("the backup daemon"))?;
+    println!("{}: Started the backup daemon (pid {}), logging to {}", style("[INFO]").cyan(), child.id(), log_path.display());
+    Ok(())
+}
+
+/// Signals the backup daemon to stop and waits for it to finish any in-flight backup and re-enable saving
+pub fn stop_backup_daemon() -> Result<(), ServmgrError> {
+    let pid_path = daemon_file_path(PID_FILE_NAME);
+    let Some(pid) = running_daemon_pid().map_err(ServmgrError::filesystem("read", &pid_path))? else {
+        println!("{}: The backup daemon is not running", style("[INFO]").cyan());
+        return Ok(());
+    };
+
+    println!("{}", style("[INFO] Stopping automatic backup schedule...").cyan());
+    // SAFETY: kill only sends a signal, the PID came from the daemon's own locked PID file
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(ServmgrError::Failed(format!("Failed to signal the backup daemon (pid {}): {}", pid, io::Error::last_os_error())));
+    }
+
+    let backing_up = read_daemon_status().ok().is_some_and(|status| status["backing_up"].is_string());
+    let status_spinner = backup_controller::new_status_spinner(
+        if backing_up { "Waiting for the in-flight backup to finish..." } else { "Waiting for the backup daemon to stop..." });
+    while matches!(running_daemon_pid(), Ok(Some(_))) {
+        thread::sleep(POLL_INTERVAL);
+    }
+    status_spinner.finish_and_clear();
+
+    println!("{}", style("[INFO] Automatic backup schedule stopped").cyan());
+    Ok(())
+}
+
+fn read_daemon_status() -> io::Result<Value> {
+    let mut stream = UnixStream::connect(daemon_file_path(SOCKET_FILE_NAME))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    serde_json::from_str(&response).map_err(io::Error::other)
+}
+
+/// Prints what the backup daemon is doing, read from its status socket
+pub fn show_backup_daemon_status(json_output: bool) -> Result<(), ServmgrError> {
+    let socket_path = daemon_file_path(SOCKET_FILE_NAME);
+    let status = match read_daemon_status() {
+        Ok(status) => status,
+        Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused) => {
+            if json_output { println!("{}", json!({"running": false})); } else {
+                println!("{} {}", style("○").red(), style("Backup daemon: NOT RUNNING").cyan());
+            }
+            return Ok(());
+        },
+        Err(err) => return Err(ServmgrError::filesystem("read the backup daemon's status from", &socket_path)(err)),
+    };
+
+    if json_output {
+        let mut status = status;
+        status["running"] = json!(true);
+        println!("{}", status);
+        return Ok(());
+    }
+
+    let unknown = || String::from("-");
+    let format_time = |value: &Value| value.as_str()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string());
+    println!("{} {}", style("●").green(), style("Backup daemon: RUNNING").cyan());
+    println!("  {:<18} {}", "PID:", status["pid"]);
+    println!("  {:<18} {}", "Started:", format_time(&status["started_at"]).unwrap_or_else(unknown));
+    println!("  {:<18} {}", "State:", match (status["stopping"].as_bool(), status["backing_up"].as_str()) {
+        (Some(true), _) => String::from("stopping"),
+        (_, Some(schedule)) => format!("backing up ({})", schedule),
+        _ => String::from("waiting"),
+    });
+    println!("  {:<18} {} taken, {} failed, {} skipped", "Backups:", status["backups_taken"], status["backups_failed"], status["backups_skipped"]);
+    println!("  {:<18} {}", "Last backup:", format_time(&status["last_backup"]["at"])
+        .map(|at| format!("{} ({}, {})", at, status["last_backup"]["schedule"].as_str().unwrap_or("-"),
+            if status["last_backup"]["success"] == true { "ok" } else { "failed" }))
+        .unwrap_or_else(unknown));
+    if let Some(at) = format_time(&status["last_skip"]["at"]) {
+        println!("  {:<18} {} ({}, {})", "Last skip:", at, status["last_skip"]["schedule"].as_str().unwrap_or("-"),
+            status["last_skip"]["reason"].as_str().unwrap_or("-"));
+    }
+    println!("  {:<18} {}", "Next backup:", format_time(&status["next_backup_at"]).unwrap_or_else(unknown));
+    for schedule in status["schedules"].as_array().into_iter().flatten() {
+        println!("  {:<18} {} {}", format!("Schedule {}:", schedule["name"].as_str().unwrap_or("-")),
+            format_time(&schedule["next_run_at"]).unwrap_or_else(unknown), style(schedule["when"].as_str().unwrap_or_default()).dim());
+    }
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use std::{io, path::{Component, Path, PathBuf}};
+use chrono::Local;
+use console::style;
+use serde_json::json;
+use crate::{backup_catalog::{self, BackupEntry}, disk_usage, world_manager::{self, LevelMetadata}};
+use crate::error::ServmgrError;
+
+/// Everything `backup show` reads out of an archive in a single pass
+struct ArchiveContents {
+    files: Vec<(PathBuf, u64)>,
+    world_root: Option<PathBuf>,
+    level_metadata: Option<LevelMetadata>,
+}
+
+/// The name of the world folder an archive was made from, taken from its first entry so only the start of the archive is read
+/// (e.g.) "world/" -> "world", older archives made by tar: "home/mcadmin/minecraft_server/world/" -> "world"
+fn read_world_name(backup: &BackupEntry) -> io::Result<Option<String>> {
+    let mut world_name = None;
+    backup.for_each_entry(|entry| {
+        let world_directory = if entry.is_dir { Some(entry.path.as_path()) } else { entry.path.parent() };
+        world_name = world_directory
+            .and_then(|directory| directory.file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+        Ok(false)
+    })?;
+    Ok(world_name)
+}
+
+fn read_archive_contents(backup: &BackupEntry) -> io::Result<ArchiveContents> {
+    let mut contents = ArchiveContents { files: Vec::new(), world_root: None, level_metadata: None };
+
+    backup.for_each_entry(|entry| {
+        if !entry.is_file { return Ok(true); }
+
+        // the shallowest level.dat marks the root of the world
+        let is_shallower_level_dat = entry.path.file_name().is_some_and(|name| name == "level.dat")
+            && contents.world_root.as_ref().is_none_or(|root| entry.path.components().count() <= root.components().count());
+        if is_shallower_level_dat {
+            contents.world_root = Some(entry.path.parent().map(Path::to_path_buf).unwrap_or_default());
+            contents.level_metadata = world_manager::read_level_metadata(entry.reader).ok();
+        }
+
+        contents.files.push((entry.path, entry.size));
+        Ok(true)
+    })?;
+
+    Ok(contents)
+}
+
+/// Dimension folders a world contains, by the folder their region files live in
+fn find_dimensions(relative_files: &[(PathBuf, u64)]) -> Vec<String> {
+    let mut dimensions: Vec<String> = relative_files.iter()
+        .filter_map(|(path, _)| {
+            let components: Vec<String> = path.components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect();
+
+            let region_index = components.iter().position(|component| component == "region")?;
+            let dimension = match &components[..region_index] {
+                [] => String::from("overworld"),
+                [folder] if folder == "DIM-1" => String::from("the_nether (DIM-1)"),
+                [folder] if folder == "DIM1" => String::from("the_end (DIM1)"),
+                [dimensions_folder, namespace, name] if dimensions_folder == "dimensions" => format!("{}:{}", namespace, name),
+                other => other.join("/"),
+            };
+            Some(dimension)
+        })
+        .collect();
+
+    dimensions.sort();
+    dimensions.dedup();
+    dimensions
+}
+
+fn format_age(backup: &BackupEntry) -> String {
+    let age = Local::now().signed_duration_since(backup.created_at);
+    match age.num_seconds() {
+        seconds if seconds < 60 => String::from("just now"),
+        seconds if seconds < 3600 => format!("{}m ago", seconds / 60),
+        seconds if seconds < 86400 => format!("{}h {}m ago", seconds / 3600, (seconds % 3600) / 60),
+        seconds => format!("{}d {}h ago", seconds / 86400, (seconds % 86400) / 3600),
+    }
+}
+
+pub fn list_minecraft_server_backups(json_output: bool) -> Result<(), ServmgrError> {
+    let backups = backup_catalog::list_backups().map_err(ServmgrError::filesystem("read", &backup_catalog::backups_directory()))?;
+
+    // an archive whose first entry can't be read is reported as corrupt, `backup verify` does the thorough check
+    let inspected: Vec<(BackupEntry, Option<String>, bool)> = backups.into_iter()
+        .map(|backup| {
+            let world_name = read_world_name(&backup);
+            let intact = world_name.is_ok();
+            (backup, world_name.ok().flatten(), intact)
+        })
+        .collect();
+
+    if json_output {
+        let entries: Vec<_> = inspected.iter()
+            .map(|(backup, world_name, intact)| json!({
+                "name": backup.name,
+                "path": backup.path,
+                "created_at": backup.created_at.to_rfc3339(),
+                "age_seconds": Local::now().signed_duration_since(backup.created_at).num_seconds(),
+                "size_bytes": backup.size,
+                "world": world_name,
+                "integrity": if *intact { "ok" } else { "corrupt" },
+                "pinned": backup.pinned,
+            }))
+            .collect();
+        println!("{}", json!(entries));
+        return Ok(());
+    }
+
+    if inspected.is_empty() {
+        println!("{}: No backups found in {}", style("[INFO]").cyan(), backup_catalog::backups_directory().display());
+        return Ok(());
+    }
+
+    // scheduled backups carry their schedule's name, which makes the name column wider
+    let name_width = inspected.iter().map(|(backup, _, _)| backup.name.len()).max().unwrap_or_default().max(40);
+    println!("{}", style(format!("{:<name_width$} {:>10}  {:<14} {:<16} {:<9} {}", "NAME", "SIZE", "AGE", "WORLD", "INTEGRITY", "PINNED")).bold());
+    for (backup, world_name, intact) in &inspected {
+        let integrity = if *intact { style("ok       ").green() } else { style("corrupt  ").red() };
+        println!("{:<name_width$} {:>10}  {:<14} {:<16} {} {}",
+            backup.name, disk_usage::format_size(backup.size), format_age(backup), world_name.as_deref().unwrap_or("-"), integrity,
+            if backup.pinned { "yes" } else { "" });
+    }
+
+    let total_size: u64 = inspected.iter().map(|(backup, _, _)| backup.size).sum();
+    println!("{}", style(format!("{} backups, {} total", inspected.len(), disk_usage::format_size(total_size))).dim());
+    Ok(())
+}
+
+pub fn show_minecraft_server_backup(selector: &str, json_output: bool) -> Result<(), ServmgrError> {
+    let backup = backup_catalog::resolve_backup(selector)?;
+    let contents = read_archive_contents(&backup)
+        .map_err(|err| ServmgrError::archive(&format!("read {}", backup.name))(err.into()))?;
+
+    // show paths relative to the world folder, older archives store it under its absolute path
+    let world_root = contents.world_root.clone().unwrap_or_default();
+    let relative_files: Vec<(PathBuf, u64)> = contents.files.iter()
+        .map(|(path, size)| (path.strip_prefix(&world_root).map(Path::to_path_buf).unwrap_or_else(|_| path.clone()), *size))
+        .collect();
+    let dimensions = find_dimensions(&relative_files);
+    let uncompressed_size: u64 = relative_files.iter().map(|(_, size)| size).sum();
+    let metadata = contents.level_metadata.as_ref();
+
+    if json_output {
+        println!("{}", json!({
+            "name": backup.name,
+            "path": backup.path,
+            "created_at": backup.created_at.to_rfc3339(),
+            "size_bytes": backup.size,
+            "uncompressed_size_bytes": uncompressed_size,
+            "world_root": contents.world_root,
+            "dimensions": dimensions,
+            "level": metadata.map(|metadata| json!({
+                "level_name": metadata.level_name,
+                "seed": metadata.seed,
+                "last_played": metadata.last_played.map(|last_played| last_played.to_rfc3339()),
+                "game_version": metadata.game_version,
+                "data_version": metadata.data_version,
+                "hardcore": metadata.hardcore,
+            })),
+            "files": relative_files.iter().map(|(path, size)| json!({"path": path, "size_bytes": size})).collect::<Vec<_>>(),
+        }));
+        return Ok(());
+    }
+
+    let unknown = || String::from("unknown");
+    println!("{}", style(format!("Backup '{}'", backup.name)).cyan().bold());
+    println!("  {:<18} {}", "Created:", backup.created_at.format("%Y-%m-%d %H:%M:%S"));
+    println!("  {:<18} {} ({} uncompressed)", "Size:", disk_usage::format_size(backup.size), disk_usage::format_size(uncompressed_size));
+    println!("  {:<18} {}", "Files:", relative_files.len());
+    println!("  {:<18} {}", "World folder:", contents.world_root.as_ref().map(|root| root.display().to_string()).unwrap_or_else(|| String::from("no level.dat found")));
+    println!("  {:<18} {}", "Dimensions:", if dimensions.is_empty() { String::from("none") } else { dimensions.join(", ") });
+    if let Some(metadata) = metadata {
+        println!("  {:<18} {}", "Level name:", metadata.level_name.clone().unwrap_or_else(unknown));
+        println!("  {:<18} {}", "Seed:", metadata.seed.map(|seed| seed.to_string()).unwrap_or_else(unknown));
+        println!("  {:<18} {}", "Last played:", metadata.last_played
+            .map(|last_played| last_played.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(unknown));
+        println!("  {:<18} {}", "Game version:", metadata.game_version.clone().unwrap_or_else(unknown));
+    }
+
+    println!("{}", style("Contents:").bold());
+    for (path, size) in &relative_files {
+        println!("  {:>10}  {}", disk_usage::format_size(*size), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::{fs, io, path::{Path, PathBuf}};
+use serde_json::{json, Value};
+use crate::archive::{ArchiveSummary, FileDigest};
+use crate::backup_catalog::{BackupEntry, BackupStorage};
+use crate::backup_repository;
+use crate::world_manager::LevelMetadata;
+
+pub const SIDECAR_EXTENSION: &str = ".manifest.json";
+const MANIFEST_VERSION: u64 = 1;
+
+/// What a backup contained when it was made, used by `backup verify` to tell whether it is still intact
+pub struct BackupManifest {
+    pub servmgr_version: String,
+    pub game_version: Option<String>,
+    pub data_version: Option<i64>,
+    pub file_count: u64,
+    pub total_size: u64,
+    pub files: Vec<FileDigest>,
+}
+
+impl BackupManifest {
+    pub fn new(summary: ArchiveSummary, level_metadata: Option<&LevelMetadata>) -> BackupManifest {
+        BackupManifest {
+            servmgr_version: String::from(env!("CARGO_PKG_VERSION")),
+            game_version: level_metadata.and_then(|metadata| metadata.game_version.clone()),
+            data_version: level_metadata.and_then(|metadata| metadata.data_version),
+            file_count: summary.files,
+            total_size: summary.bytes,
+            files: summary.digests,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "version": MANIFEST_VERSION,
+            "servmgr_version": self.servmgr_version,
+            "game_version": self.game_version,
+            "data_version": self.data_version,
+            "file_count": self.file_count,
+            "total_size": self.total_size,
+            "files": self.files.iter()
+                .map(|file| json!({"path": file.path, "size": file.size, "sha256": file.sha256}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn from_json(manifest: &Value) -> io::Result<BackupManifest> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid backup manifest ({})", message));
+        if manifest["version"].as_u64() != Some(MANIFEST_VERSION) { return Err(invalid("unsupported version")); }
+
+        let files = manifest["files"].as_array().ok_or_else(|| invalid("no file list"))?.iter()
+            .map(|file| Some(FileDigest {
+                path: PathBuf::from(file["path"].as_str()?),
+                size: file["size"].as_u64()?,
+                sha256: String::from(file["sha256"].as_str()?),
+            }))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("malformed file entry"))?;
+
+        Ok(BackupManifest {
+            servmgr_version: manifest["servmgr_version"].as_str().unwrap_or_default().to_string(),
+            game_version: manifest["game_version"].as_str().map(String::from),
+            data_version: manifest["data_version"].as_i64(),
+            file_count: manifest["file_count"].as_u64().ok_or_else(|| invalid("no file count"))?,
+            total_size: manifest["total_size"].as_u64().ok_or_else(|| invalid("no total size"))?,
+            files,
+        })
+    }
+}
+
+/// Archives get a `<archive>.manifest.json` next to them
+pub fn sidecar_path(backup_path: &Path) -> PathBuf {
+    let mut sidecar_path = backup_path.as_os_str().to_owned();
+    sidecar_path.push(SIDECAR_EXTENSION);
+    PathBuf::from(sidecar_path)
+}
+
+pub fn write_manifest(backup_path: &Path, storage: BackupStorage, manifest: &BackupManifest) -> io::Result<()> {
+    match storage {
+        BackupStorage::Archive(_) => {
+            let path = sidecar_path(backup_path);
+            let temporary_path = path.with_extension("tmp");
+            fs::write(&temporary_path, serde_json::to_vec_pretty(&manifest.to_json()).map_err(io::Error::other)?)?;
+            fs::rename(&temporary_path, &path)
+        },
+        BackupStorage::Repository => backup_repository::write_integrity_manifest(backup_path, manifest.to_json()),
+    }
+}
+
+/// The backup's manifest, `None` for backups made before servmgr wrote manifests
+pub fn read_manifest(backup: &BackupEntry) -> io::Result<Option<BackupManifest>> {
+    let manifest = match backup.storage {
+        BackupStorage::Archive(_) => match fs::read(sidecar_path(&backup.path)) {
+            Ok(contents) => Some(serde_json::from_slice(&contents).map_err(io::Error::other)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        },
+        BackupStorage::Repository => backup_repository::read_integrity_manifest(&backup.path)?,
+    };
+
+    manifest.as_ref().map(BackupManifest::from_json).transpose()
+}
@@ -0,0 +1,224 @@
+use std::{fs, io, path::Path, time::Instant};
+use clap::ValueEnum;
+use serde_json::json;
+use crate::error::ServmgrError;
+use crate::rcon::RconClient;
+use crate::text_component;
+
+/// How much of a backup players are told about in chat
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Verbosity {
+    /// Nothing
+    None,
+    /// When a backup starts, finishes or fails
+    Summary,
+    /// Every step of the backup
+    All,
+}
+
+/// Everything a backup tells players about, in the order it happens
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BackupEvent {
+    Started,
+    SavingDisabled,
+    Flushing,
+    Flushed,
+    Compressing,
+    Compressed,
+    SavingEnabled,
+    Finished,
+    Failed,
+    BackupsDisabled,
+}
+
+impl BackupEvent {
+    const ALL: [BackupEvent; 10] = [
+        BackupEvent::Started, BackupEvent::SavingDisabled, BackupEvent::Flushing, BackupEvent::Flushed, BackupEvent::Compressing,
+        BackupEvent::Compressed, BackupEvent::SavingEnabled, BackupEvent::Finished, BackupEvent::Failed, BackupEvent::BackupsDisabled,
+    ];
+
+    /// The event's table under [messages] in the notifications file
+    fn key(self) -> &'static str {
+        match self {
+            BackupEvent::Started => "started",
+            BackupEvent::SavingDisabled => "saving_disabled",
+            BackupEvent::Flushing => "flushing",
+            BackupEvent::Flushed => "flushed",
+            BackupEvent::Compressing => "compressing",
+            BackupEvent::Compressed => "compressed",
+            BackupEvent::SavingEnabled => "saving_enabled",
+            BackupEvent::Finished => "finished",
+            BackupEvent::Failed => "failed",
+            BackupEvent::BackupsDisabled => "backups_disabled",
+        }
+    }
+
+    fn verbosity(self) -> Verbosity {
+        match self {
+            BackupEvent::Started | BackupEvent::Finished | BackupEvent::Failed | BackupEvent::BackupsDisabled => Verbosity::Summary,
+            _ => Verbosity::All,
+        }
+    }
+
+    fn default_message(self) -> (&'static str, &'static str) {
+        match self {
+            BackupEvent::Started => ("Creating Server Backup...", "#FF6600"),
+            BackupEvent::SavingDisabled => ("=> Disabled auto-writing from RAM to DISK", "#A094FF"),
+            BackupEvent::Flushing => ("=> Flushing DISK to RAM...", "#A094FF"),
+            BackupEvent::Flushed => ("=> => Flushed DISK to RAM", "#A094FF"),
+            BackupEvent::Compressing => ("=> Compressing world folder...", "#A094FF"),
+            BackupEvent::Compressed => ("=> => World folder compressed and saved", "#A094FF"),
+            BackupEvent::SavingEnabled => ("=> Enabled auto-writing from RAM to DISK", "#A094FF"),
+            BackupEvent::Finished => ("Backup complete: {file} ({size}, {duration})", "dark_green"),
+            BackupEvent::Failed => ("Backup failed: {error}", "red"),
+            BackupEvent::BackupsDisabled => ("Backups Disabled", "red"),
+        }
+    }
+}
+
+/// Variables message templates can use, each one is filled in once the backup knows it
+const TEMPLATE_VARIABLES: [&str; 6] = ["file", "size", "duration", "schedule", "time", "error"];
+
+/// What is said for an event and in which color
+#[derive(Clone)]
+struct Message {
+    text: String,
+    color: String,
+}
+
+/// The notifications file, which looks like
+///
+/// verbosity = "summary"
+/// target = "@a"
+///
+/// [messages.finished]
+/// text = "Backup complete: {file} ({size}, {duration})"
+/// color = "dark_green"
+#[derive(Clone)]
+pub struct NotificationConfig {
+    pub verbosity: Verbosity,
+    pub target: String,
+    messages: Vec<(BackupEvent, Message)>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> NotificationConfig {
+        NotificationConfig {
+            verbosity: Verbosity::Summary,
+            target: String::from("@a"),
+            messages: BackupEvent::ALL.iter()
+                .map(|event| {
+                    let (text, color) = event.default_message();
+                    (*event, Message { text: String::from(text), color: String::from(color) })
+                })
+                .collect(),
+        }
+    }
+}
+
+fn is_valid_color(color: &str) -> bool {
+    text_component::is_named_color(color)
+        || (color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|character| character.is_ascii_hexdigit()))
+}
+
+/// The `{name}` variables a template uses
+fn template_variables(template: &str) -> impl Iterator<Item = &str> {
+    template.split('{').skip(1).filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+}
+
+impl NotificationConfig {
+    /// Reads the notifications file, the defaults apply to whatever it leaves out (or all of it if there is no file)
+    pub fn load(path: &Path) -> Result<NotificationConfig, ServmgrError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(NotificationConfig::default()),
+            Err(err) => return Err(ServmgrError::filesystem("read the notifications file", path)(err)),
+        };
+        let document: toml::Table = contents.parse().map_err(|err| ServmgrError::Config(format!("Failed to parse {} ({})", path.display(), err)))?;
+        let invalid = |message: String| ServmgrError::Config(format!("{}: {}", path.display(), message));
+
+        let mut config = NotificationConfig::default();
+        if let Some(verbosity) = document.get("verbosity") {
+            config.verbosity = verbosity.as_str().and_then(|verbosity| Verbosity::from_str(verbosity, true).ok())
+                .ok_or_else(|| invalid(String::from("verbosity must be one of none, summary or all")))?;
+        }
+        if let Some(target) = document.get("target") {
+            config.target = target.as_str().map(str::trim).filter(|target| !target.is_empty() && !target.contains(char::is_whitespace))
+                .map(String::from)
+                .ok_or_else(|| invalid(String::from("target must be a target selector (e.g.) \"@a\" or a player name")))?;
+        }
+
+        let Some(messages) = document.get("messages") else { return Ok(config); };
+        let messages = messages.as_table().ok_or_else(|| invalid(String::from("messages must be a table")))?;
+        for (key, message) in messages {
+            let Some(event) = BackupEvent::ALL.iter().find(|event| event.key() == key) else {
+                let keys: Vec<&str> = BackupEvent::ALL.iter().map(|event| event.key()).collect();
+                return Err(invalid(format!("unknown message '{}' (expected one of {})", key, keys.join(", "))));
+            };
+            let message = message.as_table().ok_or_else(|| invalid(format!("messages.{} must be a table", key)))?;
+            let (_, current) = config.messages.iter_mut().find(|(message_event, _)| message_event == event).expect("every event has a message");
+
+            if let Some(text) = message.get("text") {
+                let text = text.as_str().ok_or_else(|| invalid(format!("messages.{}.text must be a string", key)))?;
+                if let Some(variable) = template_variables(text).find(|variable| !TEMPLATE_VARIABLES.contains(variable)) {
+                    return Err(invalid(format!("messages.{}.text uses unknown variable {{{}}} (expected one of {{{}}})",
+                        key, variable, TEMPLATE_VARIABLES.join("}, {"))));
+                }
+                current.text = String::from(text);
+            }
+            if let Some(color) = message.get("color") {
+                current.color = color.as_str().filter(|color| is_valid_color(color)).map(String::from)
+                    .ok_or_else(|| invalid(format!("messages.{}.color must be a chat color name or #RRGGBB", key)))?;
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Tells players about a backup as it goes, filling the message templates in with what the backup knows so far
+pub struct BackupNotifier {
+    config: NotificationConfig,
+    started_at: Instant,
+    variables: Vec<(&'static str, String)>,
+}
+
+impl BackupNotifier {
+    pub fn new(config: NotificationConfig, schedule: Option<&str>) -> BackupNotifier {
+        let mut notifier = BackupNotifier { config, started_at: Instant::now(), variables: Vec::new() };
+        notifier.set("schedule", schedule.unwrap_or("manual"));
+        notifier.set("time", &chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        notifier
+    }
+
+    pub fn set(&mut self, variable: &'static str, value: &str) {
+        self.variables.retain(|(name, _)| *name != variable);
+        self.variables.push((variable, String::from(value)));
+    }
+
+    fn render(&self, template: &str) -> String {
+        let duration = format!("{:.1}s", self.started_at.elapsed().as_secs_f64());
+        let mut rendered = template.replace("{duration}", &duration);
+        for (name, value) in &self.variables {
+            rendered = rendered.replace(&format!("{{{}}}", name), value);
+        }
+        rendered
+    }
+
+    /// The `tellraw` command for an event, `None` when the verbosity leaves it out
+    pub fn command(&self, event: BackupEvent) -> Option<String> {
+        if event.verbosity() > self.config.verbosity { return None; }
+
+        let (_, message) = self.config.messages.iter().find(|(message_event, _)| *message_event == event)?;
+        let component = json!({"text": self.render(&message.text), "color": message.color});
+        Some(format!("tellraw {} {}", self.config.target, component))
+    }
+
+    /// Sends an event's message, doing nothing when the verbosity leaves it out
+    pub fn notify(&self, rcon: &mut RconClient, event: BackupEvent) -> io::Result<()> {
+        match self.command(event) {
+            Some(command) => rcon.command(&command).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::{collections::HashSet, fs, io::{self, Read, Write}, path::{Path, PathBuf}};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use crate::archive::{self, ArchiveEntry, ArchiveError, ArchiveProgress, ArchiveSummary, FileDigest};
+
+pub const REPOSITORY_DIRECTORY_NAME: &str = "repository";
+pub const MANIFEST_EXTENSION: &str = ".json";
+const MANIFESTS_DIRECTORY_NAME: &str = "manifests";
+const CHUNKS_DIRECTORY_NAME: &str = "chunks";
+const LOCK_FILE_NAME: &str = "lock";
+const MANIFEST_VERSION: u64 = 1;
+const INTEGRITY_MANIFEST_KEY: &str = "integrity";
+const CHUNK_COMPRESSION_LEVEL: i32 = 3;
+
+/// Pieces of ordinary files, and anything in a region file bigger than this, are split at this size
+const MAX_PIECE_SIZE: usize = 4 * 1024 * 1024;
+const REGION_SECTOR_SIZE: usize = 4096;
+const REGION_HEADER_SIZE: usize = 2 * REGION_SECTOR_SIZE;
+
+/// Totals of a finished incremental backup, `stored_bytes` is what the backup added to the repository
+pub struct RepositorySummary {
+    pub archive: ArchiveSummary,
+    pub stored_bytes: u64,
+}
+
+/// What garbage collection removed (or would remove)
+pub struct GarbageCollection {
+    pub chunks: u64,
+    pub bytes: u64,
+}
+
+/// The repository is laid out as
+/// backups/repository/manifests/world_backup_<timestamp>.json, one per backup
+/// backups/repository/chunks/<first two hex digits>/<sha256>, zstd compressed and shared by every backup
+pub fn repository_directory(backups_directory: &Path) -> PathBuf {
+    backups_directory.join(REPOSITORY_DIRECTORY_NAME)
+}
+
+pub fn manifests_directory(backups_directory: &Path) -> PathBuf {
+    repository_directory(backups_directory).join(MANIFESTS_DIRECTORY_NAME)
+}
+
+/// Manifests live in `repository/manifests`, so the repository is two levels up
+fn repository_of_manifest(manifest_path: &Path) -> io::Result<&Path> {
+    manifest_path.parent().and_then(Path::parent)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not inside a repository", manifest_path.display())))
+}
+
+fn chunk_path(repository: &Path, hash: &str) -> PathBuf {
+    repository.join(CHUNKS_DIRECTORY_NAME).join(&hash[..2]).join(hash)
+}
+
+fn hash_piece(piece: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(piece);
+    archive::hex_digest(hasher)
+}
+
+/// Only one backup or garbage collection may touch the repository at a time, otherwise a collection could delete
+/// chunks a backup has written but not referenced from its manifest yet
+fn lock_repository(repository: &Path) -> io::Result<fs::File> {
+    fs::create_dir_all(repository)?;
+    let lock_file = fs::File::create(repository.join(LOCK_FILE_NAME))?;
+    lock_file.try_lock().map_err(|_| io::Error::new(io::ErrorKind::WouldBlock, "The backup repository is in use by another servmgr process"))?;
+    Ok(lock_file)
+}
+
+/// Splits a region file along the chunk sectors listed in its header so a chunk that didn't change dedupes
+/// even when others in the same region did, returns the byte ranges in file order (gaps between chunks included)
+fn region_piece_ranges(region: &[u8]) -> Vec<(usize, usize)> {
+    if region.len() < REGION_HEADER_SIZE { return vec![(0, region.len())]; }
+
+    let mut chunk_ranges: Vec<(usize, usize)> = region[..REGION_SECTOR_SIZE].chunks_exact(4)
+        .filter_map(|location| {
+            let sector_offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+            let sector_count = location[3] as usize;
+            let start = sector_offset * REGION_SECTOR_SIZE;
+            let end = (start + sector_count * REGION_SECTOR_SIZE).min(region.len());
+            (sector_count > 0 && start >= REGION_HEADER_SIZE && start < end).then_some((start, end))
+        })
+        .collect();
+    chunk_ranges.sort();
+
+    let mut ranges = vec![(0, REGION_HEADER_SIZE)];
+    let mut cursor = REGION_HEADER_SIZE;
+    for (start, end) in chunk_ranges {
+        if start < cursor { continue; } // overlapping entries only happen in corrupt regions, the bytes are covered either way
+        if start > cursor { ranges.push((cursor, start)); }
+        ranges.push((start, end));
+        cursor = end;
+    }
+    if cursor < region.len() { ranges.push((cursor, region.len())); }
+    ranges
+}
+
+/// Writes a piece to the repository unless it's already there, returns its hash and the bytes written
+fn store_piece(repository: &Path, piece: &[u8]) -> io::Result<(String, u64)> {
+    let hash = hash_piece(piece);
+    let path = chunk_path(repository, &hash);
+    if path.exists() { return Ok((hash, 0)); }
+
+    let compressed = zstd::encode_all(piece, CHUNK_COMPRESSION_LEVEL)?;
+    if let Some(parent) = path.parent() { fs::create_dir_all(parent)?; }
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, &compressed)?;
+    fs::rename(&temporary_path, &path)?;
+    Ok((hash, compressed.len() as u64))
+}
+
+/// Backs `source_directory` up into the repository the manifest path belongs to, only pieces the repository doesn't
+/// have yet are written, paths in the manifest are relative like in an archive (e.g.) world/region/r.0.0.mca
+pub fn write_backup(
+    source_directory: &Path,
+    root_name: &Path,
+    manifest_path: &Path,
+    mut on_progress: impl FnMut(&ArchiveProgress),
+) -> Result<RepositorySummary, ArchiveError> {
+    let repository = repository_of_manifest(manifest_path)?.to_path_buf();
+    let _lock = lock_repository(&repository)?;
+    let entries = archive::collect_entries(source_directory, root_name)?;
+
+    let mut manifest_entries = Vec::new();
+    let mut summary = RepositorySummary { archive: ArchiveSummary { files: 0, bytes: 0, digests: Vec::new() }, stored_bytes: 0 };
+
+    for (disk_path, archive_path) in &entries {
+        let metadata = fs::symlink_metadata(disk_path).map_err(archive::error_at(disk_path))?;
+        if metadata.is_dir() {
+            manifest_entries.push(json!({"path": archive_path, "type": "dir"}));
+            continue;
+        }
+        if !metadata.is_file() { continue; } // symlinks and special files have no place in a world folder
+
+        let contents = fs::read(disk_path).map_err(archive::error_at(disk_path))?;
+        let is_region = disk_path.extension().is_some_and(|extension| extension == "mca");
+        let ranges = if is_region { region_piece_ranges(&contents) } else { vec![(0, contents.len())] };
+
+        let mut hashes = Vec::new();
+        for (start, end) in ranges {
+            for piece in contents[start..end].chunks(MAX_PIECE_SIZE) {
+                let (hash, stored_bytes) = store_piece(&repository, piece).map_err(archive::error_at(disk_path))?;
+                hashes.push(hash);
+                summary.stored_bytes += stored_bytes;
+                summary.archive.bytes += piece.len() as u64;
+                on_progress(&ArchiveProgress { current_file: disk_path, files_done: summary.archive.files, bytes_done: summary.archive.bytes });
+            }
+        }
+
+        summary.archive.files += 1;
+        summary.archive.digests.push(FileDigest { path: archive_path.clone(), size: contents.len() as u64, sha256: hash_piece(&contents) });
+        manifest_entries.push(json!({"path": archive_path, "type": "file", "size": contents.len(), "chunks": hashes}));
+    }
+
+    let manifest = json!({
+        "version": MANIFEST_VERSION,
+        "created_at": chrono::Local::now().to_rfc3339(),
+        "stored_bytes": summary.stored_bytes,
+        "entries": manifest_entries,
+    });
+
+    // the manifest is written last so a backup only shows up once every chunk it needs is in place
+    if let Some(parent) = manifest_path.parent() { fs::create_dir_all(parent).map_err(archive::error_at(parent))?; }
+    write_manifest(manifest_path, &manifest).map_err(archive::error_at(manifest_path))?;
+    Ok(summary)
+}
+
+fn write_manifest(manifest_path: &Path, manifest: &Value) -> io::Result<()> {
+    let temporary_path = manifest_path.with_extension("tmp");
+    fs::write(&temporary_path, serde_json::to_vec_pretty(manifest).map_err(io::Error::other)?)?;
+    fs::rename(&temporary_path, manifest_path)
+}
+
+/// Repository backups keep their integrity manifest (see `backup_manifest`) inside their own manifest instead of a sidecar
+pub fn write_integrity_manifest(manifest_path: &Path, integrity_manifest: Value) -> io::Result<()> {
+    let mut manifest = read_manifest(manifest_path)?;
+    manifest[INTEGRITY_MANIFEST_KEY] = integrity_manifest;
+    write_manifest(manifest_path, &manifest)
+}
+
+pub fn read_integrity_manifest(manifest_path: &Path) -> io::Result<Option<Value>> {
+    Ok(read_manifest(manifest_path)?.get(INTEGRITY_MANIFEST_KEY).cloned())
+}
+
+fn read_manifest(manifest_path: &Path) -> io::Result<Value> {
+    let manifest: Value = serde_json::from_slice(&fs::read(manifest_path)?).map_err(io::Error::other)?;
+    match manifest["version"].as_u64() {
+        Some(MANIFEST_VERSION) => Ok(manifest),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsupported manifest version in {}", manifest_path.display()))),
+    }
+}
+
+fn manifest_entries(manifest: &Value) -> &[Value] {
+    manifest["entries"].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn entry_chunks(entry: &Value) -> impl Iterator<Item = &str> {
+    entry["chunks"].as_array().into_iter().flatten().filter_map(Value::as_str)
+}
+
+/// How many bytes the backup added to the repository when it was made, used as its size for listing
+pub fn read_stored_size(manifest_path: &Path) -> io::Result<u64> {
+    Ok(read_manifest(manifest_path)?["stored_bytes"].as_u64().unwrap_or(0))
+}
+
+/// Every chunk the backup references (once each) with how much it takes up in the repository, so sizes can be
+/// totalled across backups without counting the chunks they share twice, a missing chunk takes up nothing
+pub fn read_chunk_sizes(manifest_path: &Path) -> io::Result<Vec<(String, u64)>> {
+    let repository = repository_of_manifest(manifest_path)?;
+    let manifest = read_manifest(manifest_path)?;
+    let hashes: HashSet<&str> = manifest_entries(&manifest).iter().flat_map(entry_chunks).collect();
+    Ok(hashes.into_iter()
+        .map(|hash| (String::from(hash), fs::metadata(chunk_path(repository, hash)).map_or(0, |metadata| metadata.len())))
+        .collect())
+}
+
+/// Reads a file back out of the repository one chunk at a time, checking every chunk against its hash
+struct ChunkReader<'a> {
+    repository: &'a Path,
+    hashes: Vec<&'a str>,
+    next_hash: usize,
+    current: io::Cursor<Vec<u8>>,
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buffer)?;
+            if read > 0 || self.next_hash >= self.hashes.len() { return Ok(read); }
+
+            let hash = self.hashes[self.next_hash];
+            self.next_hash += 1;
+            let path = chunk_path(self.repository, hash);
+            let piece = zstd::decode_all(fs::File::open(&path)
+                .map_err(|err| io::Error::new(err.kind(), format!("Missing chunk {} ({})", hash, err)))?)?;
+            if hash_piece(&piece) != hash {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Chunk {} is corrupt", hash)));
+            }
+            self.current = io::Cursor::new(piece);
+        }
+    }
+}
+
+/// Streams through a backup's files the same way `archive::for_each_entry` does for an archive
+pub fn for_each_entry(manifest_path: &Path, mut callback: impl FnMut(ArchiveEntry) -> io::Result<bool>) -> io::Result<()> {
+    let repository = repository_of_manifest(manifest_path)?;
+    let manifest = read_manifest(manifest_path)?;
+
+    for entry in manifest_entries(&manifest) {
+        let Some(entry_path) = entry["path"].as_str() else { continue; };
+        let is_dir = entry["type"] == "dir";
+        let mut reader = ChunkReader { repository, hashes: entry_chunks(entry).collect(), next_hash: 0, current: io::Cursor::new(Vec::new()) };
+        let archive_entry = ArchiveEntry {
+            path: PathBuf::from(entry_path), size: entry["size"].as_u64().unwrap_or(0), is_file: !is_dir, is_dir, reader: &mut reader };
+        if !callback(archive_entry)? { break; }
+    }
+    Ok(())
+}
+
+/// Recreates a backup's files under `destination`, refusing paths that would land outside of it
+pub fn extract_backup(manifest_path: &Path, destination: &Path) -> Result<(), ArchiveError> {
+    let mut result = Ok(());
+    for_each_entry(manifest_path, |entry| {
+        let is_enclosed = entry.path.components().all(|component| matches!(component, std::path::Component::Normal(_)));
+        if !is_enclosed {
+            result = Err(ArchiveError {
+                path: Some(entry.path.clone()), source: io::Error::new(io::ErrorKind::InvalidData, "Path escapes the destination") });
+            return Ok(false);
+        }
+
+        let target_path = destination.join(&entry.path);
+        let written = if entry.is_dir {
+            fs::create_dir_all(&target_path)
+        } else {
+            target_path.parent().map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::File::create(&target_path))
+                .and_then(|mut file| io::copy(entry.reader, &mut file).and_then(|_| file.flush()))
+        };
+
+        match written {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                result = Err(ArchiveError { path: Some(entry.path.clone()), source: err });
+                Ok(false)
+            },
+        }
+    }).map_err(ArchiveError::from)?;
+    result
+}
+
+/// Deletes (or with `dry_run` only counts) every chunk no manifest references anymore
+pub fn collect_garbage(backups_directory: &Path, dry_run: bool) -> io::Result<GarbageCollection> {
+    let repository = repository_directory(backups_directory);
+    let mut collected = GarbageCollection { chunks: 0, bytes: 0 };
+    if !repository.exists() { return Ok(collected); }
+    let _lock = lock_repository(&repository)?;
+
+    // a manifest that can't be read fails the whole collection, guessing could delete chunks a backup still needs
+    let mut referenced_chunks = HashSet::new();
+    let manifest_entries_on_disk = match fs::read_dir(manifests_directory(backups_directory)) {
+        Ok(entries) => entries.collect::<io::Result<Vec<_>>>()?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    for manifest_entry in manifest_entries_on_disk {
+        let manifest_path = manifest_entry.path();
+        if !manifest_path.to_string_lossy().ends_with(MANIFEST_EXTENSION) { continue; }
+
+        let manifest = read_manifest(&manifest_path)?;
+        for entry in manifest_entries(&manifest) {
+            referenced_chunks.extend(entry_chunks(entry).map(String::from));
+        }
+    }
+
+    let chunks_directory = repository.join(CHUNKS_DIRECTORY_NAME);
+    if !chunks_directory.exists() { return Ok(collected); }
+    for prefix_entry in fs::read_dir(&chunks_directory)? {
+        let prefix_path = prefix_entry?.path();
+        if !prefix_path.is_dir() { continue; }
+
+        for chunk_entry in fs::read_dir(&prefix_path)? {
+            let chunk_entry = chunk_entry?;
+            if referenced_chunks.contains(chunk_entry.file_name().to_string_lossy().as_ref()) { continue; }
+
+            collected.chunks += 1;
+            collected.bytes += chunk_entry.metadata()?.len();
+            if !dry_run { fs::remove_file(chunk_entry.path())?; }
+        }
+    }
+
+    Ok(collected)
+}
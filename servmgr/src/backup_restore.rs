@@ -0,0 +1,157 @@
+use std::{fs, io, path::{Path, PathBuf}};
+use console::style;
+use indicatif::ProgressBar;
+use crate::{archive, backup_catalog::{self, BackupEntry}, backup_controller, config, disk_usage, server_activator, world_manager};
+use crate::error::ServmgrError;
+
+const RESTORE_DIRECTORY_NAME: &str = ".restore";
+const SNAPSHOT_NAME_INFIX: &str = "_pre_restore_";
+
+/// Which backup `restore_minecraft_server_backup` should restore
+pub enum RestoreTarget<'a> {
+    Named(&'a str),
+    At(&'a str),
+}
+
+/// Restores a backup over the selected world, the current world is moved aside first and moved back if anything fails,
+/// afterwards only the newest `keep_snapshots` of those pre-restore snapshots are kept
+pub fn restore_minecraft_server_backup(target: RestoreTarget, stop_server: bool, keep_snapshots: usize) -> Result<(), ServmgrError> {
+    let backup = match target {
+        RestoreTarget::Named(selector) => backup_catalog::resolve_backup(selector)?,
+        RestoreTarget::At(timestamp) => match backup_catalog::parse_timestamp(timestamp) {
+            Some(point_in_time) => backup_catalog::resolve_backup_at(&point_in_time)?,
+            None => return Err(ServmgrError::Invalid(format!("Invalid timestamp '{}' (expected e.g. \"2026-10-01 04:00:00\")", timestamp))),
+        },
+    };
+
+    if server_activator::is_minecraft_server_running() {
+        if !stop_server {
+            return Err(ServmgrError::Invalid(String::from("The Minecraft server is running, stop it first or pass --stop to have it stopped")));
+        }
+
+        server_activator::stop_minecraft_server(&server_activator::StopOptions::default())?;
+    }
+
+    let status_spinner = backup_controller::new_status_spinner(
+        &format!("Restoring {} ({})...", backup.name, disk_usage::format_size(backup.size)));
+    let result = run_restore_steps(&backup, keep_snapshots, &status_spinner);
+    status_spinner.finish_and_clear();
+    result?;
+
+    println!("{}: Restored {} ({})", style("[INFO]").cyan(), backup.name, backup.created_at.format("%Y-%m-%d %H:%M:%S"));
+    Ok(())
+}
+
+fn run_restore_steps(backup: &BackupEntry, keep_snapshots: usize, status_spinner: &ProgressBar) -> Result<(), ServmgrError> {
+    let world_path = world_manager::active_world_path();
+    let restore_directory = config::get().server_directory.join(RESTORE_DIRECTORY_NAME);
+    let restore_timestamp = chrono::Local::now().format(backup_catalog::BACKUP_TIMESTAMP_FORMAT).to_string();
+    let staging_path = restore_directory.join(format!("staging_{}", restore_timestamp));
+    let world_name = world_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let snapshot_path = restore_directory.join(format!("{}{}{}", world_name, SNAPSHOT_NAME_INFIX, restore_timestamp));
+
+    // extract into a staging directory next to the world so the final swap is a rename on the same filesystem
+    status_spinner.println(style(format!("=> Extracting {}...", backup.name)).dim().to_string());
+    let extract_result = fs::create_dir_all(&staging_path).map_err(archive::ArchiveError::from)
+        .and_then(|_| backup.extract(&staging_path));
+    if let Err(err) = extract_result {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to extract backup archive").cyan(), err));
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(ServmgrError::archive(&format!("extract {}", backup.name))(err));
+    }
+
+    let Some(restored_world_path) = find_world_root(&staging_path) else {
+        status_spinner.println(format!("{} {}", style("○").red(), style("Backup archive does not contain a level.dat").cyan()));
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(ServmgrError::Failed(format!("{} does not contain a level.dat", backup.name)));
+    };
+
+    let level_dat_path = restored_world_path.join("level.dat");
+    if let Err(err) = fs::File::open(&level_dat_path).and_then(world_manager::read_level_metadata) {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Backup archive has an unreadable level.dat").cyan(), err));
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(ServmgrError::filesystem("read the restored", &level_dat_path)(err));
+    }
+    status_spinner.println(style("=> => Backup contains a valid world").dim().to_string());
+
+    // pre-restore snapshot of the current world
+    let has_current_world = world_path.exists();
+    if has_current_world {
+        if let Err(err) = fs::rename(&world_path, &snapshot_path) {
+            status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to snapshot the current world").cyan(), err));
+            let _ = fs::remove_dir_all(&staging_path);
+            return Err(ServmgrError::filesystem("snapshot the current world", &world_path)(err));
+        }
+        status_spinner.println(style(format!("=> Moved current world to {}", snapshot_path.display())).dim().to_string());
+    }
+
+    // swap the restored world in, rolling back to the snapshot if that fails
+    let swap_result = match world_path.parent() {
+        Some(parent) => fs::create_dir_all(parent).and_then(|_| fs::rename(&restored_world_path, &world_path)),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "World path has no parent directory")),
+    };
+
+    if let Err(err) = swap_result {
+        status_spinner.println(format!("{} {} ({})", style("○").red(), style("Failed to move the restored world into place").cyan(), err));
+        if has_current_world {
+            match fs::rename(&snapshot_path, &world_path) {
+                Ok(_) => status_spinner.println(style("=> Rolled back to the pre-restore snapshot").dim().to_string()),
+                Err(err) => status_spinner.println(format!("{} {} {} ({})",
+                    style("○").red(), style("Rollback failed, the previous world is still at").cyan(), snapshot_path.display(), err)),
+            }
+        }
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(ServmgrError::filesystem("move the restored world into", &world_path)(err));
+    }
+
+    let _ = fs::remove_dir_all(&staging_path);
+    status_spinner.println(style(format!("=> Restored world to {}", world_path.display())).dim().to_string());
+    prune_snapshots(&restore_directory, keep_snapshots, status_spinner);
+    if has_current_world && snapshot_path.exists() {
+        status_spinner.println(style(format!("Pre-restore snapshot kept at {}", snapshot_path.display())).dim().to_string());
+    }
+    Ok(())
+}
+
+/// Deletes all but the newest `keep` pre-restore snapshots (of any world), a snapshot that can't be deleted is only
+/// warned about since the restore itself went through
+fn prune_snapshots(restore_directory: &Path, keep: usize, status_spinner: &ProgressBar) {
+    let Ok(entries) = fs::read_dir(restore_directory) else { return; };
+    // the timestamp sorts in time order, the world name in front of it doesn't matter
+    let mut snapshots: Vec<(String, PathBuf)> = entries.filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (_, timestamp) = name.rsplit_once(SNAPSHOT_NAME_INFIX)?;
+            Some((String::from(timestamp), entry.path()))
+        })
+        .collect();
+    snapshots.sort_by(|a, b| b.0.cmp(&a.0));
+
+    for (_, snapshot_path) in snapshots.iter().skip(keep) {
+        match fs::remove_dir_all(snapshot_path) {
+            Ok(_) => status_spinner.println(style(format!("=> Deleted pre-restore snapshot {}", snapshot_path.display())).dim().to_string()),
+            Err(err) => status_spinner.println(format!("{}: Failed to delete pre-restore snapshot {} ({})",
+                style("[WARN]").yellow(), snapshot_path.display(), err)),
+        }
+    }
+}
+
+/// The shallowest directory under the staging directory that contains a `level.dat`, older archives store the world
+/// under its absolute path (home/mcadmin/minecraft_server/world/...) so it is not always at the top
+fn find_world_root(directory: &Path) -> Option<PathBuf> {
+    let mut directories = vec![directory.to_path_buf()];
+    while !directories.is_empty() {
+        if let Some(world_root) = directories.iter().find(|directory| directory.join("level.dat").is_file()) {
+            return Some(world_root.clone());
+        }
+
+        directories = directories.iter()
+            .filter_map(|directory| fs::read_dir(directory).ok())
+            .flat_map(|entries| entries.filter_map(Result::ok))
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+    }
+    None
+}
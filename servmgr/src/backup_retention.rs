@@ -0,0 +1,300 @@
+use std::{collections::{HashMap, HashSet}, fs, io, path::PathBuf};
+use chrono::{DateTime, Local};
+use clap::Args;
+use console::style;
+use crate::{backup_catalog::{self, BackupEntry, BackupStorage}, backup_manifest, backup_repository, disk_usage};
+use crate::error::ServmgrError;
+
+/// Maps a backup's creation time to the period (hour, day, week or month) it falls in
+type PeriodKey = fn(&DateTime<Local>) -> String;
+/// What a backup takes up on disk as named blocks with their sizes, an archive is one block while a repository
+/// backup is its manifest and every chunk it references, which other backups may share
+type StoredBlocks = Vec<(String, u64)>;
+
+/// Grandfather-father-son retention, a backup is kept if any rule keeps it
+#[derive(Args, Clone)]
+pub struct RetentionPolicy {
+    /// Number of most recent backups to always keep
+    #[arg(long, default_value = "12")]
+    pub keep_last: usize,
+
+    /// Number of hours to keep the newest backup of
+    #[arg(long, default_value = "0")]
+    pub keep_hourly: usize,
+
+    /// Number of days to keep the newest backup of
+    #[arg(long, default_value = "7")]
+    pub keep_daily: usize,
+
+    /// Number of weeks to keep the newest backup of
+    #[arg(long, default_value = "4")]
+    pub keep_weekly: usize,
+
+    /// Number of months to keep the newest backup of
+    #[arg(long, default_value = "6")]
+    pub keep_monthly: usize,
+
+    /// Maximum total size of all backups (e.g.) 50G, oldest backups are pruned first to stay under it,
+    /// chunks shared by repository backups count once
+    #[arg(long, value_parser = disk_usage::parse_size)]
+    pub max_total_size: Option<u64>,
+}
+
+/// The same defaults as the command line, for backups taken by the daemon
+impl Default for RetentionPolicy {
+    fn default() -> RetentionPolicy {
+        RetentionPolicy { keep_last: 12, keep_hourly: 0, keep_daily: 7, keep_weekly: 4, keep_monthly: 6, max_total_size: None }
+    }
+}
+
+/// Decides which backups to keep, returns the reasons each backup is kept for (no reasons means it gets pruned),
+/// newest backup first, the size cap counts a backup as its `stored_blocks` (or as one block of its size if missing)
+pub fn apply_retention_policy<'a>(
+    backups: &'a [BackupEntry],
+    policy: &RetentionPolicy,
+    stored_blocks: &'a HashMap<PathBuf, StoredBlocks>,
+) -> Vec<(&'a BackupEntry, Vec<String>)> {
+    let mut newest_first: Vec<(&BackupEntry, Vec<String>)> = backups.iter().rev().map(|backup| (backup, Vec::new())).collect();
+
+    for (index, (backup, reasons)) in newest_first.iter_mut().enumerate() {
+        if index == 0 { reasons.push(String::from("newest")); }
+        if backup.pinned { reasons.push(String::from("pinned")); }
+        if index < policy.keep_last { reasons.push(String::from("last")); }
+    }
+
+    let periods: [(&str, usize, PeriodKey); 4] = [
+        ("hourly", policy.keep_hourly, |created_at| created_at.format("%Y-%m-%d %H").to_string()),
+        ("daily", policy.keep_daily, |created_at| created_at.format("%Y-%m-%d").to_string()),
+        ("weekly", policy.keep_weekly, |created_at| created_at.format("%G-W%V").to_string()),
+        ("monthly", policy.keep_monthly, |created_at| created_at.format("%Y-%m").to_string()),
+    ];
+
+    // the newest backup of each of the most recent N periods is kept
+    for (period_name, keep_count, period_of) in periods {
+        let mut seen_periods = HashSet::new();
+        for (backup, reasons) in newest_first.iter_mut() {
+            if seen_periods.len() >= keep_count { break; }
+            if seen_periods.insert(period_of(&backup.created_at)) { reasons.push(String::from(period_name)); }
+        }
+    }
+
+    // then the size cap, dropping the oldest backups that aren't the newest or pinned, a block only stops counting
+    // once no kept backup references it (the same chunks garbage collection leaves behind)
+    if let Some(max_total_size) = policy.max_total_size {
+        let mut references: HashMap<&str, (usize, u64)> = HashMap::new();
+        for (backup, _) in newest_first.iter().filter(|(_, reasons)| !reasons.is_empty()) {
+            for (block, size) in blocks_of(backup, stored_blocks) {
+                references.entry(block).or_insert((0, size)).0 += 1;
+            }
+        }
+        let mut kept_size: u64 = references.values().map(|(_, size)| size).sum();
+
+        for (backup, reasons) in newest_first.iter_mut().rev() {
+            if kept_size <= max_total_size { break; }
+            if reasons.is_empty() || reasons.iter().any(|reason| reason == "newest" || reason == "pinned") { continue; }
+
+            reasons.clear();
+            for (block, _) in blocks_of(backup, stored_blocks) {
+                if let Some((count, size)) = references.get_mut(block) {
+                    *count -= 1;
+                    if *count == 0 { kept_size -= *size; }
+                }
+            }
+        }
+    }
+
+    newest_first
+}
+
+fn blocks_of<'a>(backup: &'a BackupEntry, stored_blocks: &'a HashMap<PathBuf, StoredBlocks>) -> Vec<(&'a str, u64)> {
+    match stored_blocks.get(&backup.path) {
+        Some(blocks) => blocks.iter().map(|(block, size)| (block.as_str(), *size)).collect(),
+        None => vec![(backup.name.as_str(), backup.size)],
+    }
+}
+
+/// What the backup takes up on disk, for repository backups the listed size is only what they added when they were
+/// made, which says nothing about the chunks still kept around for them
+fn read_stored_blocks(backup: &BackupEntry) -> io::Result<StoredBlocks> {
+    let name = backup.path.to_string_lossy().into_owned();
+    match backup.storage {
+        BackupStorage::Archive(_) => Ok(vec![(name, backup.size)]),
+        BackupStorage::Repository => {
+            let mut blocks = backup_repository::read_chunk_sizes(&backup.path)?;
+            blocks.push((name, fs::metadata(&backup.path)?.len()));
+            Ok(blocks)
+        },
+    }
+}
+
+/// Which backups a prune applies its policy to, each schedule prunes only its own backups with its own policy
+pub enum PruneScope<'a> {
+    All,
+    Manual,
+    Schedule(&'a str),
+}
+
+impl PruneScope<'_> {
+    fn includes(&self, backup: &BackupEntry) -> bool {
+        match self {
+            PruneScope::All => true,
+            PruneScope::Manual => backup.schedule.is_none(),
+            PruneScope::Schedule(schedule) => backup.schedule.as_deref() == Some(*schedule),
+        }
+    }
+}
+
+/// Deletes (or with `dry_run` only reports) every backup in scope the retention policy doesn't keep
+pub fn prune_backups(policy: &RetentionPolicy, scope: PruneScope, dry_run: bool) -> Result<(), ServmgrError> {
+    let backups: Vec<BackupEntry> = backup_catalog::list_backups()
+        .map_err(ServmgrError::filesystem("read", &backup_catalog::backups_directory()))?
+        .into_iter()
+        .filter(|backup| scope.includes(backup))
+        .collect();
+
+    let mut stored_blocks = HashMap::new();
+    if policy.max_total_size.is_some() {
+        for backup in &backups {
+            stored_blocks.insert(backup.path.clone(), read_stored_blocks(backup).map_err(ServmgrError::filesystem("read", &backup.path))?);
+        }
+    }
+
+    let decisions = apply_retention_policy(&backups, policy, &stored_blocks);
+    let mut first_error = None;
+    let mut pruned_count = 0;
+    let mut pruned_size = 0;
+    let mut pruned_from_repository = false;
+
+    for (backup, reasons) in &decisions {
+        if !reasons.is_empty() {
+            if dry_run { println!("{} {} ({})", style("keep ").green(), backup.name, reasons.join(", ")); }
+            continue;
+        }
+
+        if dry_run {
+            println!("{} {}", style("prune").red(), backup.name);
+        } else if let Err(err) = fs::remove_file(&backup.path) {
+            // the rest are still pruned, the first failure is what gets reported
+            let err = ServmgrError::filesystem("delete", &backup.path)(err);
+            eprintln!("{}: {}", style("[ERROR]").red(), err.summary());
+            first_error.get_or_insert(err);
+            continue;
+        } else {
+            let _ = fs::remove_file(backup_manifest::sidecar_path(&backup.path));
+        }
+
+        pruned_count += 1;
+        pruned_size += backup.size;
+        pruned_from_repository |= backup.storage == BackupStorage::Repository;
+    }
+
+    let verb = if dry_run { "Would prune" } else { "Pruned" };
+    println!("{}: {} {} of {} backups ({})",
+        style("[INFO]").cyan(), verb, pruned_count, decisions.len(), disk_usage::format_size(pruned_size));
+
+    // chunks only the pruned manifests referenced are garbage now
+    if pruned_from_repository && !dry_run {
+        collect_repository_garbage(false)?;
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Deletes (or with `dry_run` only reports) the chunks in the backup repository no backup references anymore
+pub fn collect_repository_garbage(dry_run: bool) -> Result<(), ServmgrError> {
+    let repository_path = backup_repository::repository_directory(&backup_catalog::backups_directory());
+    let collected = backup_repository::collect_garbage(&backup_catalog::backups_directory(), dry_run)
+        .map_err(ServmgrError::filesystem("collect garbage in", &repository_path))?;
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    println!("{}: {} {} unreferenced chunks from the backup repository ({})",
+        style("[INFO]").cyan(), verb, collected.chunks, disk_usage::format_size(collected.bytes));
+    Ok(())
+}
+
+/// Pins or unpins a backup, pinned backups are never pruned
+pub fn set_backup_pinned(selector: &str, pinned: bool) -> Result<(), ServmgrError> {
+    let backup = backup_catalog::resolve_backup(selector)?;
+
+    let marker_path = backup_catalog::pin_marker_path(&backup.path);
+    let result = if pinned {
+        fs::write(&marker_path, "")
+    } else {
+        fs::remove_file(&marker_path).or_else(|err| if err.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(err) })
+    };
+
+    result.map_err(ServmgrError::filesystem(if pinned { "pin" } else { "unpin" }, &backup.path))?;
+    println!("{}: {} {}", style("[INFO]").cyan(), if pinned { "Pinned" } else { "Unpinned" }, backup.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use crate::archive::ArchiveFormat;
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn backup(name: &str, created_at: DateTime<Local>, size: u64, storage: BackupStorage) -> BackupEntry {
+        BackupEntry {
+            name: String::from(name), path: PathBuf::from(name), created_at, size, pinned: false, storage, schedule: None }
+    }
+
+    fn policy(keep_last: usize, keep_daily: usize, keep_weekly: usize, max_total_size: Option<u64>) -> RetentionPolicy {
+        RetentionPolicy { keep_last, keep_hourly: 0, keep_daily, keep_weekly, keep_monthly: 0, max_total_size }
+    }
+
+    fn kept_names(decisions: &[(&BackupEntry, Vec<String>)]) -> Vec<String> {
+        decisions.iter().filter(|(_, reasons)| !reasons.is_empty()).map(|(backup, _)| backup.name.clone()).collect()
+    }
+
+    /// Four backups a day (at 00:00, 06:00, 12:00 and 18:00) for `days` days from 2026-01-01, a thursday, oldest first
+    fn every_six_hours(days: u32, storage: BackupStorage) -> Vec<BackupEntry> {
+        (0..days * 4).map(|index| {
+            let created_at = Local.with_ymd_and_hms(2026, 1, 1 + index / 4, (index % 4) * 6, 0, 0).unwrap();
+            backup(&created_at.format("%m-%d_%H").to_string(), created_at, GIB, storage)
+        }).collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_each_period() {
+        let backups = every_six_hours(14, BackupStorage::Archive(ArchiveFormat::TarGz));
+        let archive_sizes = HashMap::new();
+        let decisions = apply_retention_policy(&backups, &policy(2, 3, 2, None), &archive_sizes);
+        // the last two, the last of the 14th, 13th and 12th, and the last of the week before (sunday the 11th)
+        assert_eq!(kept_names(&decisions), ["01-14_18", "01-14_12", "01-13_18", "01-12_18", "01-11_18"]);
+        assert_eq!(decisions[0].1, ["newest", "last", "daily", "weekly"]);
+    }
+
+    #[test]
+    fn pinned_backups_are_always_kept() {
+        let mut backups = every_six_hours(2, BackupStorage::Archive(ArchiveFormat::TarGz));
+        backups[0].pinned = true;
+        let archive_sizes = HashMap::new();
+        let decisions = apply_retention_policy(&backups, &policy(1, 0, 0, Some(0)), &archive_sizes);
+        assert_eq!(kept_names(&decisions), ["01-02_18", "01-01_00"]);
+    }
+
+    #[test]
+    fn size_cap_prunes_the_oldest_archives_first() {
+        let backups = every_six_hours(1, BackupStorage::Archive(ArchiveFormat::TarGz));
+        let archive_sizes = HashMap::new();
+        let decisions = apply_retention_policy(&backups, &policy(4, 0, 0, Some(2 * GIB)), &archive_sizes);
+        assert_eq!(kept_names(&decisions), ["01-01_18", "01-01_12"]);
+    }
+
+    #[test]
+    fn size_cap_counts_shared_chunks_once() {
+        let backups = every_six_hours(1, BackupStorage::Repository);
+        // every backup shares a 3 GiB base chunk and adds a 100 MiB chunk of its own
+        let stored_blocks: HashMap<PathBuf, StoredBlocks> = backups.iter()
+            .map(|backup| (backup.path.clone(), vec![(String::from("base"), 3 * GIB), (backup.name.clone(), 100 * 1024 * 1024)]))
+            .collect();
+        let decisions = apply_retention_policy(&backups, &policy(4, 0, 0, Some(7 * GIB / 2)), &stored_blocks);
+        assert_eq!(kept_names(&decisions), ["01-01_18", "01-01_12", "01-01_06", "01-01_00"]);
+
+        // pruning an old backup only frees its own chunk, the base stays for the newer ones
+        let decisions = apply_retention_policy(&backups, &policy(4, 0, 0, Some(3 * GIB + 200 * 1024 * 1024)), &stored_blocks);
+        assert_eq!(kept_names(&decisions), ["01-01_18", "01-01_12"]);
+    }
+}
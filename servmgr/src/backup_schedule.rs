@@ -0,0 +1,526 @@
+use std::{fs, path::Path};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Timelike};
+use clap::ValueEnum;
+use console::style;
+use crate::{archive::ArchiveFormat, backup_catalog::BackupStorage, backup_controller, backup_notifications::Verbosity, backup_retention::RetentionPolicy, config, disk_usage};
+use crate::error::ServmgrError;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+/// How far ahead to look for a matching day, far enough for "0 0 29 2 *" to find the next leap year
+const MAX_SEARCH_DAYS: i64 = 8 * 366;
+/// Idle runs are skipped for at most this long before a backup is forced anyway
+const DEFAULT_MAX_INTERVAL_HOURS: i64 = 24;
+
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// When backups run, either from a cron expression ("0 */2 * * 1-5") or a calendar spec ("weekdays at 04:00"),
+/// with minute resolution
+pub struct Schedule {
+    months: [bool; 12],
+    days_of_month: [bool; 31],
+    days_of_week: [bool; 7], // sunday first like cron
+    // cron runs on days matching either field when both the day of month and day of week are restricted
+    match_either_day: bool,
+    times: Vec<u32>, // minutes since midnight, sorted
+}
+
+impl Schedule {
+    pub fn parse(spec: &str) -> Result<Schedule, String> {
+        let spec = spec.trim().to_lowercase();
+        let first_character = spec.chars().next().ok_or_else(|| String::from("The schedule is empty"))?;
+        if first_character == '@' || ((first_character.is_ascii_digit() || first_character == '*') && spec.split_whitespace().count() == 5) {
+            parse_cron(&spec)
+        } else {
+            parse_calendar(&spec)
+        }
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month0() as usize] { return false; }
+        let day_of_month = self.days_of_month[date.day0() as usize];
+        let day_of_week = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+        if self.match_either_day { day_of_month || day_of_week } else { day_of_month && day_of_week }
+    }
+
+    /// The next run strictly after `after`, local times skipped by a daylight saving change never run
+    pub fn next_run(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.next_runs(after, 1).into_iter().next()
+    }
+
+    pub fn next_runs(&self, after: DateTime<Local>, count: usize) -> Vec<DateTime<Local>> {
+        let mut runs = Vec::new();
+        let first_date = after.date_naive();
+
+        for day_offset in 0..MAX_SEARCH_DAYS {
+            let date = first_date + Duration::days(day_offset);
+            if !self.runs_on(date) { continue; }
+
+            for minute_of_day in &self.times {
+                let time = NaiveTime::from_num_seconds_from_midnight_opt(minute_of_day * 60, 0).unwrap_or_default();
+                let Some(run) = Local.from_local_datetime(&date.and_time(time)).earliest() else { continue; };
+                if run <= after { continue; }
+
+                runs.push(run);
+                if runs.len() >= count { return runs; }
+            }
+        }
+        runs
+    }
+}
+
+/// A cron field such as "*", "5", "1-5", "*/15", "0-30/10" or a list of those, names are allowed for months and days
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<Vec<bool>, String> {
+    let parse_value = |value: &str| -> Result<u32, String> {
+        let name_offset = if names.len() == 12 { 1 } else { 0 }; // months are 1-based, days of the week 0-based
+        names.iter().position(|name| *name == value).map(|index| index as u32 + name_offset)
+            .or_else(|| value.parse().ok())
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| format!("'{}' is not a value between {} and {}", value, min, max))
+    };
+
+    let mut matches = vec![false; (max + 1) as usize];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)
+                .ok_or_else(|| format!("'{}' has an invalid step", part))?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+                None if part.contains('/') => (parse_value(range)?, max),
+                None => { let value = parse_value(range)?; (value, value) },
+            },
+        };
+        if start > end { return Err(format!("'{}' is a backwards range", part)); }
+        for value in (start..=end).step_by(step as usize) { matches[value as usize] = true; }
+    }
+    Ok(matches)
+}
+
+/// Standard five field cron: minute, hour, day of month, month, day of week
+fn parse_cron(spec: &str) -> Result<Schedule, String> {
+    let spec = match spec {
+        "@hourly" => "0 * * * *",
+        "@daily" | "@midnight" => "0 0 * * *",
+        "@weekly" => "0 0 * * 0",
+        "@monthly" => "0 0 1 * *",
+        "@yearly" | "@annually" => "0 0 1 1 *",
+        other => other,
+    };
+    let fields: Vec<&str> = spec.split_whitespace().collect();
+    let [minute, hour, day_of_month, month, day_of_week] = fields.as_slice() else {
+        return Err(format!("'{}' is not a cron expression (expected minute hour day-of-month month day-of-week)", spec));
+    };
+
+    let minutes = parse_cron_field(minute, 0, 59, &[])?;
+    let hours = parse_cron_field(hour, 0, 23, &[])?;
+    let days_of_month = parse_cron_field(day_of_month, 1, 31, &[])?;
+    let months = parse_cron_field(month, 1, 12, &MONTH_NAMES)?;
+    let days_of_week = parse_cron_field(day_of_week, 0, 7, &DAY_NAMES)?;
+
+    let mut schedule = Schedule {
+        months: std::array::from_fn(|index| months[index + 1]),
+        days_of_month: std::array::from_fn(|index| days_of_month[index + 1]),
+        days_of_week: std::array::from_fn(|index| days_of_week[index] || (index == 0 && days_of_week[7])), // 7 is sunday too
+        match_either_day: !day_of_month.starts_with('*') && !day_of_week.starts_with('*'),
+        times: Vec::new(),
+    };
+    for hour in (0..24).filter(|hour| hours[*hour as usize]) {
+        schedule.times.extend((0..60).filter(|minute| minutes[*minute as usize]).map(|minute| hour * 60 + minute));
+    }
+    Ok(schedule)
+}
+
+/// "04:00" or "4:30" as minutes since midnight
+fn parse_time_of_day(time: &str) -> Result<u32, String> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map(|time| time.hour() * 60 + time.minute())
+        .map_err(|_| format!("'{}' is not a time of day (expected e.g. 04:00)", time))
+}
+
+/// "2h", "30m", "90min" or "2 hours" (as two tokens), in minutes
+fn parse_interval(tokens: &[&str]) -> Result<(u32, usize), String> {
+    let split_at = tokens[0].find(|character: char| !character.is_ascii_digit()).unwrap_or(tokens[0].len());
+    let (amount, unit, consumed) = match tokens[0].split_at(split_at) {
+        (amount, "") => (amount, tokens.get(1).copied().unwrap_or(""), 2),
+        (amount, unit) => (amount, unit, 1),
+    };
+
+    let amount: u32 = amount.parse().map_err(|_| format!("'{}' is not an interval (expected e.g. 2h or 30m)", tokens[0]))?;
+    let minutes = match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(amount),
+        "h" | "hr" | "hrs" | "hour" | "hours" => amount.checked_mul(60),
+        _ => return Err(format!("'{}' is not a unit of time (expected m or h)", unit)),
+    };
+    let out_of_range = || String::from("The interval must be between 1 minute and 24 hours");
+    let minutes = minutes.ok_or_else(out_of_range)?;
+    if minutes == 0 || minutes > MINUTES_PER_DAY { return Err(out_of_range()); }
+    Ok((minutes, consumed))
+}
+
+/// "30s", "90m", "12h", "7d" or "2w", for every duration setting and command line flag
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{}' is not a duration (expected e.g. 30s, 12h or 7d)", duration);
+    let split_at = duration.find(|character: char| !character.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: i64 = duration[..split_at].parse().map_err(|_| invalid())?;
+    let parsed = match duration[split_at..].trim() {
+        "s" | "sec" | "seconds" => Duration::try_seconds(amount),
+        "m" | "min" | "minutes" => Duration::try_minutes(amount),
+        "h" | "hours" => Duration::try_hours(amount),
+        "d" | "days" => Duration::try_days(amount),
+        "w" | "weeks" => Duration::try_weeks(amount),
+        _ => return Err(invalid()),
+    };
+    parsed.ok_or_else(|| format!("'{}' is too long a duration", duration))
+}
+
+fn parse_day_name(token: &str) -> Option<usize> {
+    let token = token.strip_suffix('s').filter(|token| token.len() > 3).unwrap_or(token); // "mondays"
+    DAY_NAMES.iter().position(|name| token.starts_with(name) && token.len() >= 3)
+}
+
+/// Calendar specs are a set of days followed by the times to run on them (e.g.)
+/// "every day at 04:00", "daily at 04:00, 16:00", "weekdays every 2h between 08:00 and 23:00", "sat,sun at 10:00",
+/// "every monday at 03:30", "every 6h", intervals restart at midnight (or the start of the between range)
+fn parse_calendar(spec: &str) -> Result<Schedule, String> {
+    let tokens: Vec<&str> = spec.split(|character: char| character.is_whitespace() || character == ',')
+        .filter(|token| !token.is_empty())
+        .collect();
+    let mut position = 0;
+    let mut days_of_week = [true; 7];
+
+    // the days
+    match tokens.as_slice() {
+        ["every", "day" | "days", ..] => position = 2,
+        ["daily", ..] => position = 1,
+        ["weekdays" | "weekday", ..] => { days_of_week = [false, true, true, true, true, true, false]; position = 1; },
+        ["weekends" | "weekend", ..] => { days_of_week = [true, false, false, false, false, false, true]; position = 1; },
+        _ => {
+            if tokens.first() == Some(&"every") && tokens.get(1).and_then(|token| parse_day_name(token)).is_some() { position = 1; }
+            if tokens.get(position).and_then(|token| parse_day_name(token)).is_some() {
+                days_of_week = [false; 7];
+                while let Some(token) = tokens.get(position) {
+                    match parse_day_name(token) {
+                        Some(day) => days_of_week[day] = true,
+                        None if *token == "and" => {},
+                        None => break,
+                    }
+                    position += 1;
+                }
+            }
+        },
+    }
+
+    // the times
+    let mut times = Vec::new();
+    match &tokens[position.min(tokens.len())..] {
+        ["at", rest @ ..] if !rest.is_empty() => {
+            for token in rest.iter().filter(|token| **token != "and") {
+                times.push(parse_time_of_day(token)?);
+            }
+        },
+        ["hourly"] => times.extend((0..24).map(|hour| hour * 60)),
+        ["every", rest @ ..] if !rest.is_empty() => {
+            let (interval, consumed) = parse_interval(rest)?;
+            let (start, end) = match &rest[consumed.min(rest.len())..] {
+                [] => (0, MINUTES_PER_DAY - 1),
+                ["between", start, "and", end] => (parse_time_of_day(start)?, parse_time_of_day(end)?),
+                other => return Err(format!("Unexpected '{}' (expected e.g. between 08:00 and 23:00)", other.join(" "))),
+            };
+            if start > end { return Err(String::from("The start of the between range is after its end")); }
+            times.extend((start..=end).step_by(interval as usize));
+        },
+        [] => return Err(String::from("The schedule has no times (expected e.g. \"at 04:00\" or \"every 2h\")")),
+        other => return Err(format!("Unexpected '{}' (expected e.g. \"at 04:00\" or \"every 2h\")", other.join(" "))),
+    }
+
+    times.sort();
+    times.dedup();
+    Ok(Schedule { months: [true; 12], days_of_month: [true; 31], days_of_week, match_either_day: false, times })
+}
+
+/// A schedule from the schedules file along with how its backups are taken and pruned
+pub struct NamedSchedule {
+    pub name: String,
+    pub when: String,
+    pub schedule: Schedule,
+    pub storage: BackupStorage,
+    pub level: Option<u32>,
+    pub prune: bool,
+    pub retention: RetentionPolicy,
+    /// Skips runs when no players were online or the world files are unchanged since the schedule's last backup, off
+    /// unless the schedule turns it on
+    pub skip_when_idle: bool,
+    pub max_interval: Duration,
+    pub save_timeout: Duration,
+    /// Overrides the verbosity of the notifications file for this schedule's backups
+    pub notifications: Option<Verbosity>,
+}
+
+/// Schedule names end up in backup file names, so they are kept to lowercase letters, digits and dashes
+pub fn is_valid_schedule_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|character| character.is_ascii_lowercase() || character.is_ascii_digit() || character == '-')
+}
+
+/// A one-off schedule given on the command line, backed up as tar.gz and pruned with the default policy
+pub fn ad_hoc_schedule(when: &str) -> Result<NamedSchedule, ServmgrError> {
+    Ok(NamedSchedule {
+        name: String::from("auto"),
+        when: String::from(when),
+        schedule: Schedule::parse(when).map_err(|err| ServmgrError::Invalid(format!("Invalid schedule '{}': {}", when, err)))?,
+        storage: BackupStorage::Archive(ArchiveFormat::TarGz),
+        level: None,
+        prune: true,
+        retention: RetentionPolicy::default(),
+        skip_when_idle: false,
+        max_interval: Duration::hours(DEFAULT_MAX_INTERVAL_HOURS),
+        save_timeout: backup_controller::DEFAULT_SAVE_TIMEOUT,
+        notifications: None,
+    })
+}
+
+/// Reads every schedule out of a schedules file, which looks like
+///
+/// [schedules.hourly]
+/// when = "every 1h"
+/// incremental = true
+/// keep_last = 24
+///
+/// [schedules.nightly]
+/// when = "0 4 * * *"
+/// format = "tar-zst"
+/// level = 10
+/// keep_daily = 14
+/// max_total_size = "50G"
+/// skip_when_idle = true
+/// max_interval = "7d"
+/// save_timeout = "10m"
+/// notifications = "none"
+pub fn load_schedules(path: &Path) -> Result<Vec<NamedSchedule>, ServmgrError> {
+    let contents = fs::read_to_string(path).map_err(ServmgrError::filesystem("read the schedules file", path))?;
+    let document: toml::Table = contents.parse().map_err(|err| ServmgrError::Config(format!("Failed to parse {} ({})", path.display(), err)))?;
+    let Some(schedule_tables) = document.get("schedules").and_then(|schedules| schedules.as_table()) else {
+        return Err(ServmgrError::Config(format!("{} has no [schedules.<name>] sections", path.display())));
+    };
+
+    let mut schedules = Vec::new();
+    for (name, table) in schedule_tables {
+        let invalid = |message: String| ServmgrError::Config(format!("Schedule '{}' in {}: {}", name, path.display(), message));
+        if !is_valid_schedule_name(name) { return Err(invalid(String::from("names may only contain a-z, 0-9 and -"))); }
+        if name == "manual" { return Err(invalid(String::from("'manual' is reserved for backups taken with `backup man`"))); }
+        let table = table.as_table().ok_or_else(|| invalid(String::from("expected a table")))?;
+
+        let when = table.get("when").and_then(|when| when.as_str()).ok_or_else(|| invalid(String::from("missing when = \"...\"")))?;
+        let schedule = Schedule::parse(when).map_err(invalid)?;
+
+        let integer = |key: &str| -> Result<Option<u64>, ServmgrError> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(value) => value.as_integer().and_then(|value| u64::try_from(value).ok()).map(Some)
+                    .ok_or_else(|| invalid(format!("{} must be a positive integer", key))),
+            }
+        };
+
+        let format = match table.get("format") {
+            None => ArchiveFormat::TarGz,
+            Some(format) => format.as_str().and_then(|format| ArchiveFormat::from_str(format, true).ok())
+                .ok_or_else(|| invalid(String::from("format must be one of tar-gz, tar-zst, tar-xz or zip")))?,
+        };
+        let incremental = table.get("incremental").map(|incremental| incremental.as_bool()
+            .ok_or_else(|| invalid(String::from("incremental must be true or false")))).transpose()?.unwrap_or(false);
+        let storage = if incremental { BackupStorage::Repository } else { BackupStorage::Archive(format) };
+        let level = integer("level")?.map(|level| level as u32);
+        if let BackupStorage::Archive(format) = storage && let Err(err) = format.check_level(level) {
+            return Err(invalid(err.to_string()));
+        }
+
+        let defaults = RetentionPolicy::default();
+        let retention = RetentionPolicy {
+            keep_last: integer("keep_last")?.map_or(defaults.keep_last, |value| value as usize),
+            keep_hourly: integer("keep_hourly")?.map_or(defaults.keep_hourly, |value| value as usize),
+            keep_daily: integer("keep_daily")?.map_or(defaults.keep_daily, |value| value as usize),
+            keep_weekly: integer("keep_weekly")?.map_or(defaults.keep_weekly, |value| value as usize),
+            keep_monthly: integer("keep_monthly")?.map_or(defaults.keep_monthly, |value| value as usize),
+            max_total_size: table.get("max_total_size").map(|size| size.as_str()
+                .ok_or_else(|| String::from("expected a size such as \"50G\"")).and_then(disk_usage::parse_size).map_err(invalid))
+                .transpose()?,
+        };
+        let prune = table.get("prune").map(|prune| prune.as_bool()
+            .ok_or_else(|| invalid(String::from("prune must be true or false")))).transpose()?.unwrap_or(true);
+        let skip_when_idle = table.get("skip_when_idle").map(|skip| skip.as_bool()
+            .ok_or_else(|| invalid(String::from("skip_when_idle must be true or false")))).transpose()?.unwrap_or(false);
+        let duration = |key: &str, default: Duration| -> Result<Duration, ServmgrError> {
+            match table.get(key) {
+                None => Ok(default),
+                Some(duration) => duration.as_str().ok_or_else(|| format!("{} must be a duration such as \"24h\"", key))
+                    .and_then(parse_duration).map_err(invalid),
+            }
+        };
+        let max_interval = duration("max_interval", Duration::hours(DEFAULT_MAX_INTERVAL_HOURS))?;
+        let save_timeout = duration("save_timeout", backup_controller::DEFAULT_SAVE_TIMEOUT)?;
+        let notifications = table.get("notifications").map(|notifications| notifications.as_str()
+            .and_then(|notifications| Verbosity::from_str(notifications, true).ok())
+            .ok_or_else(|| invalid(String::from("notifications must be one of none, summary or all")))).transpose()?;
+
+        schedules.push(NamedSchedule {
+            name: name.clone(), when: String::from(when), schedule, storage, level, prune, retention, skip_when_idle, max_interval,
+            save_timeout, notifications,
+        });
+    }
+
+    if schedules.is_empty() { return Err(ServmgrError::Config(format!("{} has no schedules", path.display()))); }
+    Ok(schedules)
+}
+
+/// A duration to the minute (e.g.) "45m", "3h 20m" or "2d 4h", or to the second under a minute
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::minutes(1) { return format!("{}s", duration.num_seconds()); }
+    match duration.num_minutes() {
+        minutes if minutes < 60 => format!("{}m", minutes),
+        minutes if minutes < 1440 => format!("{}h {}m", minutes / 60, minutes % 60),
+        minutes => format!("{}d {}h", minutes / 1440, (minutes % 1440) / 60),
+    }
+}
+
+/// Prints the upcoming runs of every schedule (or just one, or an ad hoc spec) in the local timezone, soonest first
+pub fn print_next_runs(name: Option<&str>, when: Option<&str>, count: usize) -> Result<(), ServmgrError> {
+    let mut schedules = match when {
+        Some(when) => vec![ad_hoc_schedule(when)?],
+        None => load_schedules(&config::get().schedules_path)?,
+    };
+
+    if let Some(name) = name {
+        schedules.retain(|schedule| schedule.name == name);
+        if schedules.is_empty() {
+            return Err(ServmgrError::Invalid(format!("No schedule named '{}' in {}", name, config::get().schedules_path.display())));
+        }
+    }
+
+    let now = Local::now();
+    let mut runs: Vec<(DateTime<Local>, &NamedSchedule)> = schedules.iter()
+        .flat_map(|schedule| schedule.schedule.next_runs(now, count).into_iter().map(move |run| (run, schedule)))
+        .collect();
+    runs.sort_by_key(|(run, _)| *run);
+    runs.truncate(count);
+
+    if runs.is_empty() {
+        println!("{}: None of the schedules ever run", style("[INFO]").cyan());
+        return Ok(());
+    }
+
+    println!("{}", style(format!("{:<30} {:<16} {:<10} {}", "WHEN", "SCHEDULE", "IN", "SPEC")).bold());
+    for (run, schedule) in runs {
+        let until = format_duration(run.signed_duration_since(now));
+        println!("{:<30} {:<16} {:<10} {}", run.format("%a %Y-%m-%d %H:%M %:z"), schedule.name, until, style(&schedule.when).dim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn next_runs(spec: &str, after: DateTime<Local>, count: usize) -> Vec<DateTime<Local>> {
+        Schedule::parse(spec).unwrap().next_runs(after, count)
+    }
+
+    #[test]
+    fn cron_runs_on_matching_minutes() {
+        // 2026-01-05 is a monday
+        let runs = next_runs("0 */2 * * 1-5", at(2026, 1, 5, 3, 0), 2);
+        assert_eq!(runs, [at(2026, 1, 5, 4, 0), at(2026, 1, 5, 6, 0)]);
+        assert_eq!(next_runs("30 4 * * sat", at(2026, 1, 5, 0, 0), 1), [at(2026, 1, 10, 4, 30)]);
+        assert_eq!(next_runs("@daily", at(2026, 1, 5, 0, 0), 1), [at(2026, 1, 6, 0, 0)]);
+    }
+
+    #[test]
+    fn cron_matches_either_day_when_both_are_restricted() {
+        // the 1st of the month or any friday, 2026-01-02 is a friday
+        let runs = next_runs("0 0 1 * fri", at(2026, 1, 1, 12, 0), 2);
+        assert_eq!(runs, [at(2026, 1, 2, 0, 0), at(2026, 1, 9, 0, 0)]);
+    }
+
+    #[test]
+    fn cron_finds_the_next_leap_day() {
+        assert_eq!(next_runs("0 0 29 2 *", at(2026, 1, 1, 0, 0), 1), [at(2028, 2, 29, 0, 0)]);
+    }
+
+    #[test]
+    fn invalid_cron_expressions_are_rejected() {
+        for spec in ["60 * * * *", "0 24 * * *", "0 0 0 * *", "0 0 * 13 *", "0 0 * * 8", "*/0 * * * *", "5-1 * * * *", "0 0 * foo *"] {
+            assert!(Schedule::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn calendar_specs() {
+        assert_eq!(next_runs("every day at 04:00", at(2026, 1, 5, 5, 0), 1), [at(2026, 1, 6, 4, 0)]);
+        assert_eq!(next_runs("daily at 04:00, 16:00", at(2026, 1, 5, 5, 0), 2), [at(2026, 1, 5, 16, 0), at(2026, 1, 6, 4, 0)]);
+        assert_eq!(next_runs("sat,sun at 10:00", at(2026, 1, 5, 0, 0), 2), [at(2026, 1, 10, 10, 0), at(2026, 1, 11, 10, 0)]);
+        assert_eq!(next_runs("every monday at 03:30", at(2026, 1, 5, 4, 0), 1), [at(2026, 1, 12, 3, 30)]);
+        assert_eq!(next_runs("weekends at 09:00", at(2026, 1, 5, 0, 0), 1), [at(2026, 1, 10, 9, 0)]);
+        assert_eq!(next_runs("weekdays every 2h between 08:00 and 23:00", at(2026, 1, 9, 22, 0), 2), [at(2026, 1, 12, 8, 0), at(2026, 1, 12, 10, 0)]);
+        assert_eq!(next_runs("every 90min", at(2026, 1, 5, 0, 0), 2), [at(2026, 1, 5, 1, 30), at(2026, 1, 5, 3, 0)]);
+        assert_eq!(next_runs("every 6 hours", at(2026, 1, 5, 19, 0), 1), [at(2026, 1, 6, 0, 0)]);
+    }
+
+    #[test]
+    fn invalid_calendar_specs_are_rejected() {
+        for spec in ["", "every day", "daily at 25:00", "every 2x", "every 2h between 10:00 and 08:00", "every 2h from 08:00", "whenever"] {
+            assert!(Schedule::parse(spec).is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn intervals_must_be_between_a_minute_and_a_day() {
+        assert_eq!(parse_interval(&["24h"]), Ok((MINUTES_PER_DAY, 1)));
+        assert_eq!(parse_interval(&["1", "minute"]), Ok((1, 2)));
+        for interval in ["0m", "0h", "25h", "1441m"] {
+            assert!(parse_interval(&[interval]).is_err(), "{}", interval);
+        }
+    }
+
+    #[test]
+    fn huge_intervals_are_rejected_instead_of_overflowing() {
+        // 71582789 hours is just over u32::MAX minutes
+        for interval in ["71582789h", "4294967295h", "4294967296m", "99999999999999999999h"] {
+            assert!(parse_interval(&[interval]).is_err(), "{}", interval);
+            assert!(Schedule::parse(&format!("every {}", interval)).is_err(), "{}", interval);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::seconds(30)));
+        assert_eq!(parse_duration("90m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("12 hours"), Ok(Duration::hours(12)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::days(7)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::weeks(2)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::zero()));
+        for duration in ["", "12", "h", "-1h", "1.5h", "3y"] {
+            assert!(parse_duration(duration).is_err(), "{:?}", duration);
+        }
+    }
+
+    #[test]
+    fn huge_durations_are_rejected_instead_of_panicking() {
+        for duration in ["999999999999999w", "9223372036854775807s", "99999999999999999999d"] {
+            assert!(parse_duration(duration).is_err(), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn durations_are_formatted_to_the_minute() {
+        assert_eq!(format_duration(Duration::seconds(42)), "42s");
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(200)), "3h 20m");
+        assert_eq!(format_duration(Duration::hours(52)), "2d 4h");
+    }
+}
@@ -0,0 +1,140 @@
+use std::{fs, io, path::{Path, PathBuf}};
+use chrono::{DateTime, Local};
+use console::style;
+use serde_json::{json, Value};
+use crate::{backup_controller::BackupOutcome, backup_daemon, backup_schedule, config, disk_usage};
+use crate::error::ServmgrError;
+
+const BACKUP_STATE_FILE_NAME: &str = "servmgr_backup_state.json";
+
+pub fn backup_state_path() -> PathBuf {
+    config::get().server_directory.join(BACKUP_STATE_FILE_NAME)
+}
+
+/// The state is always an object, valid JSON that isn't one is as corrupt as invalid JSON
+fn read_state(path: &Path) -> io::Result<Value> {
+    match fs::read(path) {
+        Ok(contents) => match serde_json::from_slice(&contents) {
+            Ok(state @ Value::Object(_)) => Ok(state),
+            Ok(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "not a JSON object")),
+            Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(json!({})),
+        Err(err) => Err(err),
+    }
+}
+
+/// What the state file says about the last backups and the next scheduled one, an empty object before the first backup
+pub fn read_backup_state() -> io::Result<Value> {
+    read_state(&backup_state_path())
+}
+
+/// Reads, changes and atomically rewrites the state file so a reader never sees it half written, the daemon and a
+/// manual backup can update it at the same time so the whole update holds a lock on a file next to it
+fn update_state(change: impl FnOnce(&mut Value)) -> io::Result<()> {
+    let path = backup_state_path();
+    let lock_file = fs::OpenOptions::new().write(true).create(true).truncate(false).open(path.with_extension("lock"))?;
+    lock_file.lock()?;
+
+    let mut state = match read_state(&path) {
+        Ok(state) => state,
+        // rewritten from scratch rather than leaving `backup status` broken for good
+        Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+            eprintln!("{}: {} is corrupt, starting it over ({})", style("[WARN]").yellow(), path.display(), err);
+            json!({})
+        },
+        Err(err) => return Err(err),
+    };
+    change(&mut state);
+
+    let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    let result = serde_json::to_vec_pretty(&state).map_err(io::Error::other)
+        .and_then(|contents| fs::write(&temporary_path, contents))
+        .and_then(|_| fs::rename(&temporary_path, &path));
+    if result.is_err() { let _ = fs::remove_file(&temporary_path); }
+    result
+}
+
+/// Records how a backup went, manual backups have no schedule
+pub fn record_attempt(started_at: DateTime<Local>, schedule: Option<&str>, result: &Result<BackupOutcome, ServmgrError>) -> io::Result<()> {
+    let duration_seconds = Local::now().signed_duration_since(started_at).num_milliseconds() as f64 / 1000.0;
+    let attempt = json!({
+        "at": started_at.to_rfc3339(),
+        "schedule": schedule.unwrap_or("manual"),
+        "duration_seconds": duration_seconds,
+        "success": result.is_ok(),
+    });
+
+    update_state(|state| {
+        state["last_attempt"] = attempt.clone();
+        match result {
+            Ok(outcome) => {
+                state["last_success"] = attempt;
+                state["last_success"]["name"] = json!(outcome.name);
+                state["last_success"]["size"] = json!(outcome.size);
+            },
+            Err(err) => {
+                state["last_failure"] = attempt;
+                state["last_failure"]["reason"] = json!(err.summary());
+            },
+        }
+    })
+}
+
+/// Records the daemon's next scheduled run, `None` once it stops
+pub fn record_next_run(next_run: Option<(DateTime<Local>, &str)>) -> io::Result<()> {
+    update_state(|state| {
+        state["next_run"] = next_run.map_or(Value::Null, |(at, schedule)| json!({"at": at.to_rfc3339(), "schedule": schedule}));
+    })
+}
+
+/// Prints the last backup attempt, success and failure along with the next scheduled run, fails when the last
+/// successful backup is older than `max_age` (or there never was one) so monitoring can alert on the exit code
+pub fn show_backup_status(max_age: chrono::Duration, json_output: bool) -> Result<(), ServmgrError> {
+    let state_path = backup_state_path();
+    let mut state = read_state(&state_path).map_err(ServmgrError::filesystem("read", &state_path))?;
+
+    let parse_time = |value: &Value| value.as_str()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Local));
+    let last_success_age = parse_time(&state["last_success"]["at"]).map(|at| Local::now().signed_duration_since(at));
+    let healthy = last_success_age.is_some_and(|age| age <= max_age);
+    let stale_error = || ServmgrError::Failed(match last_success_age {
+        Some(age) => format!("The last successful backup is {} old (max {})",
+            backup_schedule::format_duration(age), backup_schedule::format_duration(max_age)),
+        None => String::from("There has never been a successful backup"),
+    });
+    let daemon_pid = backup_daemon::running_daemon_pid().ok().flatten();
+    if daemon_pid.is_none() { state["next_run"] = Value::Null; } // left over from a daemon that didn't shut down cleanly
+
+    if json_output {
+        state["healthy"] = json!(healthy);
+        state["max_age_seconds"] = json!(max_age.num_seconds());
+        state["last_success_age_seconds"] = json!(last_success_age.map(|age| age.num_seconds()));
+        state["daemon_pid"] = json!(daemon_pid);
+        println!("{}", state);
+        return if healthy { Ok(()) } else { Err(stale_error()) };
+    }
+
+    let format_time = |value: &Value| parse_time(value).map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string());
+    let describe = |attempt: &Value, detail: Option<String>| match format_time(&attempt["at"]) {
+        Some(at) => format!("{} ({}, {:.1}s{})", at, attempt["schedule"].as_str().unwrap_or("-"),
+            attempt["duration_seconds"].as_f64().unwrap_or_default(), detail.map(|detail| format!(", {}", detail)).unwrap_or_default()),
+        None => String::from("-"),
+    };
+
+    let (marker, health) = if healthy { (style("●").green(), "OK") } else { (style("○").red(), "STALE") };
+    println!("{} {}", marker, style(format!("Backups: {}", health)).cyan());
+    println!("  {:<16} {}", "Last attempt:", describe(&state["last_attempt"],
+        state["last_attempt"]["success"].as_bool().map(|success| String::from(if success { "ok" } else { "failed" }))));
+    println!("  {:<16} {}", "Last success:", describe(&state["last_success"], state["last_success"]["name"].as_str()
+        .map(|name| format!("{}, {}", name, disk_usage::format_size(state["last_success"]["size"].as_u64().unwrap_or_default())))));
+    println!("  {:<16} {}", "Last failure:", describe(&state["last_failure"], state["last_failure"]["reason"].as_str().map(String::from)));
+    println!("  {:<16} {}", "Next run:", match format_time(&state["next_run"]["at"]) {
+        Some(at) => format!("{} ({})", at, state["next_run"]["schedule"].as_str().unwrap_or("-")),
+        None if daemon_pid.is_none() => String::from("- (backup daemon not running)"),
+        None => String::from("-"),
+    });
+
+    if healthy { Ok(()) } else { Err(stale_error()) }
+}
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, path::PathBuf};
+use console::style;
+use sha2::{Digest, Sha256};
+use crate::{archive, backup_catalog::{self, BackupEntry}, backup_manifest, disk_usage, world_manager};
+use crate::error::ServmgrError;
+
+const REGION_SECTOR_SIZE: u64 = 4096;
+const REGION_HEADER_SIZE: u64 = 2 * REGION_SECTOR_SIZE;
+/// Only the first few problems of a backup are printed, one bad disk sector can otherwise produce thousands
+const MAX_REPORTED_PROBLEMS: usize = 10;
+
+/// The outcome of verifying a single backup
+struct Verification {
+    files: u64,
+    bytes: u64,
+    checksums_verified: bool,
+    problems: Vec<String>,
+}
+
+/// Checks a region file's location table points at chunks that are actually in the file, a truncated copy fails this
+fn check_region_header(contents: &[u8]) -> Result<(), String> {
+    if contents.is_empty() { return Ok(()); } // the server creates empty region files before it writes chunks to them
+    if (contents.len() as u64) < REGION_HEADER_SIZE {
+        return Err(format!("truncated region header ({} bytes)", contents.len()));
+    }
+
+    for (index, location) in contents[..REGION_SECTOR_SIZE as usize].chunks_exact(4).enumerate() {
+        let sector_offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as u64;
+        let sector_count = location[3] as u64;
+        if sector_offset == 0 && sector_count == 0 { continue; }
+        if sector_count == 0 {
+            return Err(format!("chunk {} has an invalid location (sector {}, 0 sectors)", index, sector_offset));
+        }
+
+        let start = sector_offset * REGION_SECTOR_SIZE;
+        if start < REGION_HEADER_SIZE || (sector_offset + sector_count) * REGION_SECTOR_SIZE > contents.len() as u64 {
+            return Err(format!("chunk {} points outside the file (sector {}, {} sectors)", index, sector_offset, sector_count));
+        }
+
+        // every chunk starts with its length, which has to fit in the sectors it was given
+        let start = start as usize;
+        let chunk_length = u32::from_be_bytes([contents[start], contents[start + 1], contents[start + 2], contents[start + 3]]) as u64;
+        if chunk_length == 0 || chunk_length + 4 > sector_count * REGION_SECTOR_SIZE {
+            return Err(format!("chunk {} has an invalid length ({} bytes in {} sectors)", index, chunk_length, sector_count));
+        }
+    }
+    Ok(())
+}
+
+/// Re-reads every file in a backup, comparing it against the backup's manifest when it has one, and checks that the
+/// world's level.dat parses and its region files are intact
+fn verify_backup(backup: &BackupEntry) -> Verification {
+    let mut verification = Verification { files: 0, bytes: 0, checksums_verified: false, problems: Vec::new() };
+
+    let manifest = match backup_manifest::read_manifest(backup) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            verification.problems.push(format!("unreadable manifest ({})", err));
+            None
+        },
+    };
+    let mut expected_files: HashMap<PathBuf, (u64, String)> = manifest.as_ref()
+        .map(|manifest| manifest.files.iter().map(|file| (file.path.clone(), (file.size, file.sha256.clone()))).collect())
+        .unwrap_or_default();
+    verification.checksums_verified = manifest.is_some();
+
+    let mut level_dat: Option<(PathBuf, Vec<u8>)> = None;
+    let mut stopped_early = false;
+    let read_result = backup.for_each_entry(|entry| {
+        if !entry.is_file { return Ok(true); }
+
+        let mut contents = Vec::with_capacity(entry.size as usize);
+        if let Err(err) = entry.reader.read_to_end(&mut contents) {
+            verification.problems.push(format!("{}: unreadable ({})", entry.path.display(), err));
+            expected_files.remove(&entry.path);
+            stopped_early = true;
+            return Ok(false); // the rest of a compressed stream can't be trusted after a read error
+        }
+        verification.files += 1;
+        verification.bytes += contents.len() as u64;
+
+        if contents.len() as u64 != entry.size {
+            verification.problems.push(format!("{}: truncated ({} of {} bytes)", entry.path.display(), contents.len(), entry.size));
+        }
+
+        if manifest.is_some() {
+            match expected_files.remove(&entry.path) {
+                Some((size, sha256)) => {
+                    let mut hasher = Sha256::new();
+                    hasher.update(&contents);
+                    if size != contents.len() as u64 || archive::hex_digest(hasher) != sha256 {
+                        verification.problems.push(format!("{}: checksum mismatch", entry.path.display()));
+                    }
+                },
+                None => verification.problems.push(format!("{}: not in the manifest", entry.path.display())),
+            }
+        }
+
+        if entry.path.extension().is_some_and(|extension| extension == "mca")
+            && let Err(err) = check_region_header(&contents) {
+            verification.problems.push(format!("{}: {}", entry.path.display(), err));
+        }
+
+        // the shallowest level.dat is the world's
+        let is_shallower_level_dat = entry.path.file_name().is_some_and(|name| name == "level.dat")
+            && level_dat.as_ref().is_none_or(|(path, _)| entry.path.components().count() < path.components().count());
+        if is_shallower_level_dat {
+            level_dat = Some((entry.path, contents));
+        }
+        Ok(true)
+    });
+
+    if let Err(err) = read_result {
+        verification.problems.push(format!("archive is corrupt or truncated ({})", err));
+        stopped_early = true;
+    }
+
+    // whatever comes after a read error was never looked at, so it isn't reported as missing
+    if stopped_early {
+        if !expected_files.is_empty() {
+            verification.problems.push(format!("{} more files in the manifest could not be checked", expected_files.len()));
+        }
+        return verification;
+    }
+
+    let mut missing_files: Vec<&PathBuf> = expected_files.keys().collect();
+    missing_files.sort();
+    for path in missing_files {
+        verification.problems.push(format!("{}: missing from the backup", path.display()));
+    }
+    if let Some(manifest) = &manifest && manifest.file_count != verification.files {
+        verification.problems.push(format!("expected {} files, found {}", manifest.file_count, verification.files));
+    }
+
+    match level_dat {
+        Some((path, contents)) => if let Err(err) = world_manager::read_level_metadata(contents.as_slice()) {
+            verification.problems.push(format!("{}: unreadable ({})", path.display(), err));
+        },
+        None => verification.problems.push(String::from("no level.dat found")),
+    }
+
+    verification
+}
+
+/// Verifies one backup, or every backup when `selector` is `None`, fails if any of them is corrupt
+pub fn verify_minecraft_server_backups(selector: Option<&str>) -> Result<(), ServmgrError> {
+    let backups = match selector {
+        Some(selector) => vec![backup_catalog::resolve_backup(selector)?],
+        None => backup_catalog::list_backups().map_err(ServmgrError::filesystem("read", &backup_catalog::backups_directory()))?,
+    };
+
+    let mut corrupt_count = 0;
+    for backup in &backups {
+        let verification = verify_backup(backup);
+        let checksums = if verification.checksums_verified { "checksums verified" } else { "no manifest, checksums not checked" };
+
+        if verification.problems.is_empty() {
+            println!("{} {} {}", style("●").green(), style(&backup.name).cyan(),
+                style(format!("ok ({} files, {}, {})", verification.files, disk_usage::format_size(verification.bytes), checksums)).dim());
+            continue;
+        }
+
+        corrupt_count += 1;
+        println!("{} {} {}", style("○").red(), style(&backup.name).cyan(), style("CORRUPT").red());
+        for problem in verification.problems.iter().take(MAX_REPORTED_PROBLEMS) {
+            println!("{}", style(format!("=> {}", problem)).dim());
+        }
+        if verification.problems.len() > MAX_REPORTED_PROBLEMS {
+            println!("{}", style(format!("=> ...and {} more", verification.problems.len() - MAX_REPORTED_PROBLEMS)).dim());
+        }
+    }
+
+    if corrupt_count > 0 {
+        return Err(ServmgrError::Failed(format!("{} of {} backups are corrupt", corrupt_count, backups.len())));
+    }
+    println!("{}: Verified {} backups", style("[INFO]").cyan(), backups.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A region file with a single chunk at `sector_offset` taking `sector_count` sectors, `chunk_length` bytes long
+    fn region(file_sectors: usize, sector_offset: u32, sector_count: u8, chunk_length: u32) -> Vec<u8> {
+        let mut contents = vec![0; file_sectors * REGION_SECTOR_SIZE as usize];
+        contents[..4].copy_from_slice(&(sector_offset << 8 | sector_count as u32).to_be_bytes());
+        let start = sector_offset as usize * REGION_SECTOR_SIZE as usize;
+        if start + 4 <= contents.len() { contents[start..start + 4].copy_from_slice(&chunk_length.to_be_bytes()); }
+        contents
+    }
+
+    #[test]
+    fn intact_regions_pass() {
+        assert_eq!(check_region_header(&[]), Ok(()));
+        assert_eq!(check_region_header(&region(2, 0, 0, 0)), Ok(()));
+        assert_eq!(check_region_header(&region(4, 2, 2, 5000)), Ok(()));
+    }
+
+    #[test]
+    fn corrupt_regions_are_reported() {
+        assert!(check_region_header(&[0; 100]).is_err());
+        assert!(check_region_header(&region(3, 2, 2, 100)).is_err()); // truncated
+        assert!(check_region_header(&region(3, 1, 1, 100)).is_err()); // inside the header
+        assert!(check_region_header(&region(3, 2, 1, 0)).is_err());
+        assert!(check_region_header(&region(3, 2, 1, 4096)).is_err());
+    }
+
+    #[test]
+    fn zero_sector_chunks_are_reported_instead_of_read_past_the_end() {
+        assert!(check_region_header(&region(2, 2, 0, 0)).is_err());
+        assert!(check_region_header(&region(3, 2, 0, 100)).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use std::{env, fs, io, path::{Path, PathBuf}, sync::OnceLock};
+use console::style;
+use serde_json::{json, Map, Value};
+use crate::backup_notifications::NotificationConfig;
+use crate::backup_schedule;
+use crate::error::ServmgrError;
+use crate::rcon_password;
+use crate::server_properties::ServerProperties;
+
+pub const DEFAULT_CONFIG_PATH: &str = "/home/mcadmin/minecraft_server/servmgr.toml";
+/// Names the config file in place of the default, `--config` takes precedence over it
+const CONFIG_PATH_VARIABLE: &str = "SERVMGR_CONFIG";
+/// Longest crash.backoff and crash.max_backoff, a server left down for longer than this is better left stopped
+const MAX_CRASH_BACKOFF: chrono::Duration = chrono::Duration::days(1);
+
+/// One key of servmgr.toml, each can also be set with `SERVMGR_<SECTION>_<KEY>` (e.g.) SERVMGR_BACKUP_DIRECTORY
+struct Setting {
+    section: &'static str,
+    key: &'static str,
+    default: &'static str,
+    description: &'static str,
+}
+
+const SETTINGS: [Setting; 21] = [
+    Setting { section: "server", key: "directory", default: "/home/mcadmin/minecraft_server",
+        description: "Directory the server runs in, holding server.jar, server.properties and the worlds" },
+    Setting { section: "server", key: "world", default: "",
+        description: "World folder backed up and restored, relative to the server directory (empty uses level-name from server.properties)" },
+    Setting { section: "server", key: "stop_timeout", default: "2m",
+        description: "How long the server gets to save and exit after `stop` before it is sent SIGTERM (e.g.) 90s or 5m" },
+    Setting { section: "jvm", key: "java", default: "java",
+        description: "Java executable the server runs with" },
+    Setting { section: "jvm", key: "jar", default: "server.jar",
+        description: "Server jar, relative to the server directory" },
+    Setting { section: "jvm", key: "min_memory", default: "1G",
+        description: "Initial heap size (-Xms)" },
+    Setting { section: "jvm", key: "max_memory", default: "6G",
+        description: "Maximum heap size (-Xmx), the server crashes with an OutOfMemoryError past it" },
+    Setting { section: "jvm", key: "args", default: "",
+        description: "Extra JVM arguments, separated by spaces" },
+    Setting { section: "crash", key: "restart", default: "true",
+        description: "Whether the supervisor restarts the server when it crashes (exits without being asked to stop)" },
+    Setting { section: "crash", key: "max_restarts", default: "3",
+        description: "Crashes within crash.window the server is restarted after, it is left stopped after the next one" },
+    Setting { section: "crash", key: "window", default: "1h",
+        description: "Window crashes are counted in for crash.max_restarts" },
+    Setting { section: "crash", key: "backoff", default: "10s",
+        description: "Delay before restarting after a crash, doubled for every further crash within crash.window" },
+    Setting { section: "crash", key: "max_backoff", default: "5m",
+        description: "Longest delay before restarting after a crash" },
+    Setting { section: "restart", key: "times", default: "",
+        description: "Times of day the server is restarted at with a countdown for players, (e.g.) [\"04:00\"]" },
+    Setting { section: "restart", key: "backup", default: "false",
+        description: "Whether the world is backed up right before a daily restart" },
+    Setting { section: "rcon", key: "host", default: "",
+        description: "RCON host (empty uses server-ip from server.properties, or 127.0.0.1)" },
+    Setting { section: "rcon", key: "port", default: "",
+        description: "RCON port (empty uses rcon.port from server.properties)" },
+    Setting { section: "rcon", key: "password_file", default: "",
+        description: "File holding the RCON password on its first line (e.g.) a Docker secret at /run/secrets/rcon_password, relative to the server directory (empty uses rcon.password from server.properties)" },
+    Setting { section: "backup", key: "directory", default: "backups",
+        description: "Directory backups are written to, relative to the server directory" },
+    Setting { section: "backup", key: "schedules", default: "backup_schedules.toml",
+        description: "Schedules file for the backup daemon, relative to the server directory" },
+    Setting { section: "backup", key: "notifications", default: "backup_notifications.toml",
+        description: "In-game notifications file for backups, relative to the server directory" },
+];
+
+/// The TOML type a setting is written as, environment variables are always strings
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    Boolean,
+    Integer,
+    List,
+}
+
+impl Setting {
+    fn name(&self) -> String {
+        format!("{}.{}", self.section, self.key)
+    }
+
+    fn kind(&self) -> Kind {
+        match (self.section, self.key) {
+            ("crash", "restart") | ("restart", "backup") => Kind::Boolean,
+            ("crash", "max_restarts") => Kind::Integer,
+            ("jvm", "args") | ("restart", "times") => Kind::List,
+            _ => Kind::Text,
+        }
+    }
+
+    /// The value from servmgr.toml as the environment variable would give it, `None` if it has the wrong type
+    fn file_value(&self, value: &toml::Value) -> Option<String> {
+        match (self.kind(), value) {
+            (Kind::Text | Kind::List, toml::Value::String(value)) => Some(value.clone()),
+            (Kind::Text | Kind::Integer, toml::Value::Integer(value)) => Some(value.to_string()), // rcon.port = 25575
+            (Kind::Boolean, toml::Value::Boolean(value)) => Some(value.to_string()),
+            (Kind::List, toml::Value::Array(values)) => values.iter().map(toml::Value::as_str).collect::<Option<Vec<_>>>().map(|values| values.join(" ")),
+            _ => None,
+        }
+    }
+
+    /// The default as servmgr.toml has it
+    fn default_toml(&self) -> String {
+        match self.kind() {
+            Kind::Text => format!("\"{}\"", self.default),
+            Kind::Boolean | Kind::Integer => String::from(self.default),
+            Kind::List => String::from("[]"),
+        }
+    }
+
+    fn environment_variable(&self) -> String {
+        format!("SERVMGR_{}_{}", self.section, self.key).to_uppercase()
+    }
+}
+
+/// Where a setting's value came from
+#[derive(Clone, Copy)]
+enum Source {
+    Default,
+    File,
+    Environment,
+}
+
+impl Source {
+    fn name(self) -> &'static str {
+        match self {
+            Source::Default => "default",
+            Source::File => "servmgr.toml",
+            Source::Environment => "environment",
+        }
+    }
+}
+
+pub struct JvmSettings {
+    pub java: String,
+    pub jar: String,
+    pub min_memory: String,
+    pub max_memory: String,
+    pub args: Vec<String>,
+}
+
+impl JvmSettings {
+    /// The full command line the server is started with
+    pub fn command_line(&self) -> Vec<String> {
+        let mut command_line = vec![self.java.clone(), format!("-Xms{}", self.min_memory), format!("-Xmx{}", self.max_memory)];
+        command_line.extend(self.args.iter().cloned());
+        command_line.extend([String::from("-jar"), self.jar.clone(), String::from("--nogui")]);
+        command_line
+    }
+}
+
+/// How the supervisor handles the server crashing
+pub struct CrashSettings {
+    pub restart: bool,
+    pub max_restarts: usize,
+    pub window: chrono::Duration,
+    pub backoff: chrono::Duration,
+    pub max_backoff: chrono::Duration,
+}
+
+/// When the supervisor restarts the server by itself
+pub struct RestartSettings {
+    pub times: Vec<chrono::NaiveTime>,
+    pub backup: bool,
+}
+
+impl CrashSettings {
+    /// The delay before restarting after the `crash_count`th crash within the window, doubling from the first up to
+    /// crash.max_backoff
+    pub fn restart_delay(&self, crash_count: usize) -> chrono::Duration {
+        let doublings = crash_count.saturating_sub(1).min(16) as u32;
+        self.backoff.checked_mul(1 << doublings).map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+/// Where servmgr finds the server and its files, read from servmgr.toml with environment variables taking precedence
+pub struct ServmgrConfig {
+    /// The config file, which doesn't have to exist
+    pub path: PathBuf,
+    pub server_directory: PathBuf,
+    pub world: Option<String>,
+    pub stop_timeout: chrono::Duration,
+    pub jvm: JvmSettings,
+    pub crash: CrashSettings,
+    pub restart: RestartSettings,
+    pub rcon_host: Option<String>,
+    pub rcon_port: Option<u16>,
+    pub rcon_password_file: Option<PathBuf>,
+    pub backup_directory: PathBuf,
+    pub schedules_path: PathBuf,
+    pub notifications_path: PathBuf,
+    values: Vec<(String, Source)>, // in the order of SETTINGS
+}
+
+static CONFIG: OnceLock<ServmgrConfig> = OnceLock::new();
+
+/// The config file to use, `--config` first, then $SERVMGR_CONFIG and the default location, along with whether it was
+/// named explicitly (and so has to exist)
+pub fn config_path(config_path: Option<&Path>) -> (PathBuf, bool) {
+    match config_path.map(Path::to_path_buf).or_else(|| env::var_os(CONFIG_PATH_VARIABLE).map(PathBuf::from)) {
+        Some(config_path) => (config_path, true),
+        None => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+    }
+}
+
+/// Loads the configuration every command reads its paths from, once at startup
+pub fn init(config_path: Option<&Path>) -> Result<(), ServmgrError> {
+    let config = load(config_path)?;
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+pub fn get() -> &'static ServmgrConfig {
+    CONFIG.get().expect("the configuration is loaded before any command runs")
+}
+
+fn is_valid_memory_size(size: &str) -> bool {
+    size.strip_suffix(['K', 'k', 'M', 'm', 'G', 'g'])
+        .is_some_and(|amount| !amount.is_empty() && amount.chars().all(|character| character.is_ascii_digit()))
+}
+
+fn load(config_path: Option<&Path>) -> Result<ServmgrConfig, ServmgrError> {
+    let (path, explicit) = self::config_path(config_path);
+    let document: toml::Table = match fs::read_to_string(&path) {
+        Ok(contents) => contents.parse().map_err(|err| ServmgrError::Config(format!("Failed to parse {} ({})", path.display(), err)))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => toml::Table::new(),
+        Err(err) => return Err(ServmgrError::filesystem("read the config file", &path)(err)),
+    };
+    let invalid = |message: String| ServmgrError::Config(format!("{}: {}", path.display(), message));
+
+    // anything the file has that isn't a setting is most likely a typo
+    for (section, table) in &document {
+        let table = table.as_table().ok_or_else(|| invalid(format!("[{}] must be a table", section)))?;
+        if let Some(key) = table.keys().find(|key| !SETTINGS.iter().any(|setting| setting.section == section && setting.key == key.as_str())) {
+            return Err(invalid(format!("unknown setting {}.{}", section, key)));
+        }
+    }
+
+    let mut values = Vec::new();
+    for setting in &SETTINGS {
+        let file_value = document.get(setting.section).and_then(|table| table.get(setting.key));
+        let value = match (env::var(setting.environment_variable()), file_value) {
+            (Ok(value), _) => (value, Source::Environment),
+            (Err(_), Some(file_value)) => match setting.file_value(file_value) {
+                Some(value) => (value, Source::File),
+                None => return Err(invalid(format!("{} must be {}", setting.name(), match setting.kind() {
+                    Kind::Text => "a string",
+                    Kind::Boolean => "true or false",
+                    Kind::Integer => "a whole number",
+                    Kind::List => "a list of strings",
+                }))),
+            },
+            (Err(_), None) => (String::from(setting.default), Source::Default),
+        };
+        values.push(value);
+    }
+
+    let index = |name: &str| SETTINGS.iter().position(|setting| setting.name() == name).expect("every setting read is declared");
+    let value = |name: &str| -> &str { values[index(name)].0.trim() };
+    // says where the bad value came from, the file or an environment variable
+    let invalid_setting = |name: &str, message: &str| match values[index(name)].1 {
+        Source::Environment => ServmgrError::Config(format!("${}: {} {}", SETTINGS[index(name)].environment_variable(), name, message)),
+        _ => invalid(format!("{} {}", name, message)),
+    };
+    let optional = |name: &str| Some(value(name)).filter(|value| !value.is_empty()).map(String::from);
+
+    let server_directory = PathBuf::from(value("server.directory"));
+    if !server_directory.is_absolute() { return Err(invalid_setting("server.directory", "must be an absolute path")); }
+    let in_server_directory = |name: &str| server_directory.join(value(name));
+
+    for name in ["jvm.min_memory", "jvm.max_memory"] {
+        if !is_valid_memory_size(value(name)) { return Err(invalid_setting(name, "must be a size such as 512M or 6G")); }
+    }
+    let duration = |name: &str| backup_schedule::parse_duration(value(name)).map_err(|err| invalid_setting(name, &err));
+    let backoff = |name: &str| duration(name).and_then(|backoff| if backoff <= MAX_CRASH_BACKOFF { Ok(backoff) } else {
+        Err(invalid_setting(name, &format!("must be at most {}", backup_schedule::format_duration(MAX_CRASH_BACKOFF))))
+    });
+    let crash = CrashSettings {
+        restart: value("crash.restart").parse::<bool>().map_err(|_| invalid_setting("crash.restart", "must be true or false"))?,
+        max_restarts: value("crash.max_restarts").parse::<usize>().map_err(|_| invalid_setting("crash.max_restarts", "must be a whole number"))?,
+        window: duration("crash.window")?,
+        backoff: backoff("crash.backoff")?,
+        max_backoff: backoff("crash.max_backoff")?,
+    };
+    let restart = RestartSettings {
+        times: value("restart.times").split_whitespace()
+            .map(|time| chrono::NaiveTime::parse_from_str(time, "%H:%M"))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid_setting("restart.times", "must be a list of times of day such as \"04:00\""))?,
+        backup: value("restart.backup").parse::<bool>().map_err(|_| invalid_setting("restart.backup", "must be true or false"))?,
+    };
+    let stop_timeout = duration("server.stop_timeout")?;
+    let rcon_port = optional("rcon.port").map(|port| port.parse::<u16>()
+        .map_err(|_| invalid_setting("rcon.port", "must be a port number"))).transpose()?;
+
+    Ok(ServmgrConfig {
+        path,
+        world: optional("server.world"),
+        stop_timeout,
+        jvm: JvmSettings {
+            java: String::from(value("jvm.java")),
+            jar: String::from(value("jvm.jar")),
+            min_memory: String::from(value("jvm.min_memory")),
+            max_memory: String::from(value("jvm.max_memory")),
+            args: value("jvm.args").split_whitespace().map(String::from).collect(),
+        },
+        crash,
+        restart,
+        rcon_host: optional("rcon.host"),
+        rcon_port,
+        rcon_password_file: optional("rcon.password_file").map(|path| server_directory.join(path)),
+        backup_directory: in_server_directory("backup.directory"),
+        schedules_path: in_server_directory("backup.schedules"),
+        notifications_path: in_server_directory("backup.notifications"),
+        server_directory,
+        values,
+    })
+}
+
+/// Where the RCON password will be taken from when `-p` isn't given, never the password itself
+fn rcon_password_source() -> String {
+    let found = ServerProperties::load_default().and_then(|properties| rcon_password::find_rcon_password(&properties));
+    match found {
+        Ok(Some(found)) => String::from(found.source.name()),
+        Ok(None) => String::from("not set (asked for in a terminal)"),
+        Err(err) => format!("unavailable ({})", err),
+    }
+}
+
+/// Prints every setting with its value and where it came from
+pub fn show_config(json_output: bool) -> Result<(), ServmgrError> {
+    let config = get();
+
+    if json_output {
+        let mut settings = Map::new();
+        for (setting, (value, source)) in SETTINGS.iter().zip(&config.values) {
+            settings.insert(setting.name(), json!({"value": value, "source": source.name(), "environment_variable": setting.environment_variable()}));
+        }
+        println!("{}", json!({"path": config.path, "exists": config.path.exists(), "settings": Value::Object(settings),
+            "rcon_password_source": rcon_password_source()}));
+        return Ok(());
+    }
+
+    println!("{}", style(format!("Config file: {}{}", config.path.display(), if config.path.exists() { "" } else { " (not found, using defaults)" })).cyan());
+    println!("{}", style(format!("  {:<26} {:<48} {}", "SETTING", "VALUE", "SOURCE")).bold());
+    for (setting, (value, source)) in SETTINGS.iter().zip(&config.values) {
+        let value = if value.is_empty() { String::from("-") } else { value.clone() };
+        println!("  {:<26} {:<48} {}", setting.name(), value, match source {
+            Source::Environment => format!("{} (${})", source.name(), setting.environment_variable()),
+            _ => String::from(source.name()),
+        });
+    }
+    println!("{}", style(format!("RCON password: {}", rcon_password_source())).cyan());
+    Ok(())
+}
+
+/// Checks the config file along with the schedules, notifications and RCON password files it points to
+pub fn validate_config() -> Result<(), ServmgrError> {
+    let config = get();
+    println!("{} {}", style("●").green(), style(format!("Config: {}", if config.path.exists() { config.path.display().to_string() } else { String::from("defaults") })).cyan());
+
+    let mut first_error = None;
+    let mut check = |name: &str, path: &Path, result: Result<(), ServmgrError>| match result {
+        Ok(()) => println!("{} {}", style("●").green(), style(format!("{}: {}", name, path.display())).cyan()),
+        Err(err) => {
+            println!("{} {} ({})", style("○").red(), style(format!("{}: {}", name, path.display())).cyan(), err.summary());
+            first_error.get_or_insert(err);
+        },
+    };
+
+    check("Server directory", &config.server_directory, if config.server_directory.is_dir() { Ok(()) } else {
+        Err(ServmgrError::Config(String::from("not a directory")))
+    });
+    check("Schedules", &config.schedules_path, if config.schedules_path.exists() {
+        backup_schedule::load_schedules(&config.schedules_path).map(|_| ())
+    } else {
+        Err(ServmgrError::Config(String::from("not found, the backup daemon needs it unless it is given --when")))
+    });
+    check("Notifications", &config.notifications_path, NotificationConfig::load(&config.notifications_path).map(|_| ()));
+    if let Some(path) = &config.rcon_password_file {
+        check("RCON password file", path, rcon_password::read_secrets_file(path).map(|_| ()).map_err(|err| ServmgrError::Config(err.to_string())));
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Writes a servmgr.toml holding every setting at its default, with a comment saying what it does
+pub fn init_config(config_path: Option<&Path>, force: bool) -> Result<(), ServmgrError> {
+    let (path, _) = self::config_path(config_path);
+    if path.exists() && !force {
+        return Err(ServmgrError::Invalid(format!("{} already exists (use --force to overwrite it)", path.display())));
+    }
+
+    let mut contents = String::from("# servmgr configuration, every setting can be overridden with the environment variable named above it\n");
+    let mut section = "";
+    for setting in &SETTINGS {
+        if setting.section != section {
+            section = setting.section;
+            contents.push_str(&format!("\n[{}]\n", section));
+        }
+        contents.push_str(&format!("# {} (${})\n{} = {}\n", setting.description, setting.environment_variable(), setting.key, setting.default_toml()));
+    }
+
+    fs::write(&path, contents).map_err(ServmgrError::filesystem("write", &path))?;
+    println!("{}: Wrote {}", style("[INFO]").cyan(), path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use super::*;
+
+    fn crash_settings(backoff: Duration, max_backoff: Duration) -> CrashSettings {
+        CrashSettings { restart: true, max_restarts: 3, window: Duration::hours(1), backoff, max_backoff }
+    }
+
+    #[test]
+    fn restart_delay_doubles_up_to_the_max_backoff() {
+        let crash = crash_settings(Duration::seconds(10), Duration::minutes(5));
+        let delays: Vec<i64> = (1..=7).map(|crash_count| crash.restart_delay(crash_count).num_seconds()).collect();
+        assert_eq!(delays, [10, 20, 40, 80, 160, 300, 300]);
+    }
+
+    #[test]
+    fn restart_delay_doesnt_overflow_with_a_large_backoff() {
+        let crash = crash_settings(Duration::MAX / 2, Duration::MAX);
+        assert_eq!(crash.restart_delay(usize::MAX), Duration::MAX);
+        let crash = crash_settings(MAX_CRASH_BACKOFF, MAX_CRASH_BACKOFF);
+        assert_eq!(crash.restart_delay(100), MAX_CRASH_BACKOFF);
+    }
+
+    #[test]
+    fn settings_are_read_as_their_toml_type() {
+        let setting = |section: &str, key: &str| SETTINGS.iter().find(|setting| setting.section == section && setting.key == key).unwrap();
+        let restart = setting("crash", "restart");
+        assert_eq!(restart.file_value(&toml::Value::Boolean(false)), Some(String::from("false")));
+        assert_eq!(restart.file_value(&toml::Value::String(String::from("true"))), None);
+        let max_restarts = setting("crash", "max_restarts");
+        assert_eq!(max_restarts.file_value(&toml::Value::Integer(5)), Some(String::from("5")));
+        assert_eq!(max_restarts.file_value(&toml::Value::String(String::from("5"))), None);
+        assert_eq!(restart.default_toml(), "true");
+        assert_eq!(max_restarts.default_toml(), "3");
+        assert_eq!(setting("crash", "window").default_toml(), "\"1h\"");
+    }
+}
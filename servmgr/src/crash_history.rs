@@ -0,0 +1,139 @@
+use std::{fs, io, os::unix::process::ExitStatusExt, path::{Path, PathBuf}, process::ExitStatus, time::SystemTime};
+use chrono::{DateTime, Local};
+use console::style;
+use serde_json::{json, Value};
+use crate::{backup_schedule, config};
+use crate::error::ServmgrError;
+
+const CRASH_HISTORY_FILE_NAME: &str = "servmgr_crashes.json";
+/// Incidents kept in the history, older ones are dropped as new ones are recorded
+const MAX_INCIDENTS: usize = 50;
+const CRASH_REPORTS_DIRECTORY_NAME: &str = "crash-reports";
+
+/// A server exit nobody asked for, with what the server left behind about it
+pub struct Crash {
+    pub at: DateTime<Local>,
+    pub status: ExitStatus,
+    pub uptime: chrono::Duration,
+    /// The last lines the server wrote to its console (stdout and stderr), which is where an OutOfMemoryError ends up
+    pub log_tail: Vec<String>,
+    pub crash_report: Option<PathBuf>,
+}
+
+impl Crash {
+    /// The "Description:" line of the crash report, the server writes one when it catches the error itself
+    fn report_description(&self) -> Option<String> {
+        let report = fs::read_to_string(self.crash_report.as_ref()?).ok()?;
+        report.lines()
+            .find_map(|line| line.strip_prefix("Description: "))
+            .map(|description| String::from(description.trim()))
+    }
+
+    /// The likeliest reason the server went down, from the crash report, the console output and the exit status
+    pub fn cause(&self) -> String {
+        if let Some(description) = self.report_description() { return description; }
+        if let Some(error) = self.log_tail.iter().rev().find_map(|line| line.find("OutOfMemoryError").map(|start| &line[start..])) {
+            return format!("{} (raise jvm.max_memory)", error.trim());
+        }
+        match (self.status.code(), self.status.signal()) {
+            (_, Some(libc::SIGKILL)) => String::from("Killed by SIGKILL (the kernel's OOM killer does this when memory runs out)"),
+            (_, Some(signal)) => format!("Killed by signal {}", signal),
+            (Some(code), _) => format!("Exited with code {}", code),
+            (None, None) => String::from("Exited"),
+        }
+    }
+}
+
+pub fn crash_history_path() -> PathBuf {
+    config::get().server_directory.join(CRASH_HISTORY_FILE_NAME)
+}
+
+/// The newest crash report the server wrote since `since`, reports are named after when they were written but their
+/// modification time is what tells them apart from older ones
+pub fn find_crash_report(since: DateTime<Local>) -> Option<PathBuf> {
+    let since = SystemTime::from(since);
+    fs::read_dir(config::get().server_directory.join(CRASH_REPORTS_DIRECTORY_NAME)).ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, path)| *modified >= since && path.extension().is_some_and(|extension| extension == "txt"))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+fn read_history(path: &Path) -> io::Result<Vec<Value>> {
+    match fs::read(path) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(io::Error::other),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Every recorded crash, oldest first
+pub fn read_crash_history() -> io::Result<Vec<Value>> {
+    read_history(&crash_history_path())
+}
+
+/// Adds a crash to the history, `restart_delay` is `None` when the server was left stopped
+pub fn record_crash(crash: &Crash, restart_delay: Option<chrono::Duration>) -> io::Result<()> {
+    let path = crash_history_path();
+    let mut history = read_history(&path)?;
+    history.push(json!({
+        "at": crash.at.to_rfc3339(),
+        "exit_code": crash.status.code(),
+        "signal": crash.status.signal(),
+        "uptime_seconds": crash.uptime.num_seconds(),
+        "cause": crash.cause(),
+        "crash_report": crash.crash_report,
+        "restarted": restart_delay.is_some(),
+        "restart_delay_seconds": restart_delay.map(|delay| delay.num_seconds()),
+        "log_tail": crash.log_tail,
+    }));
+    let excess = history.len().saturating_sub(MAX_INCIDENTS);
+    history.drain(..excess);
+
+    // written to the side and renamed over so a reader never sees it half written
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, serde_json::to_vec_pretty(&history).map_err(io::Error::other)?)?;
+    fs::rename(&temporary_path, &path)
+}
+
+/// Prints the last `count` crashes newest first, with the console output leading up to each one if `show_log`
+pub fn show_crash_history(count: usize, show_log: bool, json_output: bool) -> Result<(), ServmgrError> {
+    let path = crash_history_path();
+    let history = read_history(&path).map_err(ServmgrError::filesystem("read", &path))?;
+    let incidents: Vec<&Value> = history.iter().rev().take(count).collect();
+
+    if json_output {
+        println!("{}", json!(incidents));
+        return Ok(());
+    }
+
+    if incidents.is_empty() {
+        println!("{}: The Minecraft server hasn't crashed under servmgr", style("[INFO]").cyan());
+        return Ok(());
+    }
+
+    println!("{}", style(format!("{:<20} {:<10} {:<11} {}", "TIME", "UPTIME", "RESTARTED", "CAUSE")).bold());
+    for incident in &incidents {
+        let at = incident["at"].as_str()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| String::from("-"));
+        let uptime = backup_schedule::format_duration(chrono::Duration::seconds(incident["uptime_seconds"].as_i64().unwrap_or_default()));
+        let restarted = match incident["restart_delay_seconds"].as_i64() {
+            Some(delay) => style(format!("{:<11}", format!("after {}", backup_schedule::format_duration(chrono::Duration::seconds(delay))))).green(),
+            None => style(format!("{:<11}", "no")).red(),
+        };
+        println!("{:<20} {:<10} {} {}", at, uptime, restarted, incident["cause"].as_str().unwrap_or("-"));
+        if let Some(crash_report) = incident["crash_report"].as_str() {
+            println!("{}", style(format!("  crash report: {}", crash_report)).dim());
+        }
+        if show_log {
+            for line in incident["log_tail"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                println!("  {}", style(line).dim());
+            }
+        }
+    }
+    println!("{}", style(format!("{} of {} recorded crashes, history in {}", incidents.len(), history.len(), path.display())).dim());
+    Ok(())
+}
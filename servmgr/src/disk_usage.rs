@@ -0,0 +1,69 @@
+use std::{fs, path::Path};
+
+/// Total size in bytes of every file under a directory (symlinks are not followed)
+pub fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else { return 0; };
+
+    entries.filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok().map(|metadata| (entry, metadata)))
+        .map(|(entry, metadata)| if metadata.is_dir() { directory_size(&entry.path()) } else { metadata.len() })
+        .sum()
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 { format!("{} {}", bytes, UNITS[0]) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+/// Parses a size such as "512M", "50G" or "1.5T" (binary units, a bare number is bytes)
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let unit_start = size.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(size.len());
+    let (number, unit) = size.split_at(unit_start);
+
+    let number: f64 = number.trim().parse().map_err(|_| format!("Invalid size '{}'", size))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("Invalid size unit in '{}' (use K, M, G or T)", size)),
+    };
+
+    // a cast would quietly turn these into 0 or the largest size
+    let bytes = number * multiplier as f64;
+    if !bytes.is_finite() || bytes < 0.0 || bytes >= u64::MAX as f64 {
+        return Err(format!("Invalid size '{}' (expected e.g. 512M or 50G)", size));
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("50 GiB"), Ok(50 << 30));
+        assert_eq!(parse_size("1.5T"), Ok(3 << 39));
+        assert_eq!(parse_size("0"), Ok(0));
+    }
+
+    #[test]
+    fn invalid_sizes_are_rejected_instead_of_clamped() {
+        for size in ["", "G", "5X", "-5G", "-0.5", "1e400", "NaN", "inf", "99999999T"] {
+            assert!(parse_size(size).is_err(), "{:?}", size);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::{error::Error, fmt, io, path::{Path, PathBuf}};
+use console::style;
+use crate::archive::ArchiveError;
+
+/// Every way a servmgr command can fail, each class exits with its own code so scripts can react to specific failures
+/// (2 is left to clap for usage errors)
+#[derive(Debug)]
+pub enum ServmgrError {
+    /// The command ran but what it checked didn't hold (e.g.) a corrupt backup or a stale last backup
+    Failed(String),
+    /// A servmgr file (e.g.) backup_schedules.toml or server.properties holds something invalid
+    Config(String),
+    /// The RCON interface couldn't be reached or isn't answering, the server may be stopped or still booting
+    RconConnection(io::Error),
+    /// The server rejected the RCON password
+    RconAuthentication(io::Error),
+    /// The server was reached but a command sent to it failed
+    RconCommand { command: String, source: io::Error },
+    /// Reading or writing a file or directory failed
+    Filesystem { action: String, path: PathBuf, source: io::Error },
+    /// Writing, reading or extracting a backup failed
+    Archive { action: String, source: ArchiveError },
+    /// A helper process (e.g.) the server's start script couldn't be run or exited with an error
+    Process { program: String, source: io::Error },
+    /// An argument names something that doesn't exist or the server's state doesn't allow the command
+    Invalid(String),
+}
+
+impl ServmgrError {
+    /// Connection failures and rejected passwords look alike coming out of the RCON client, apart from their kind
+    pub fn rcon(source: io::Error) -> ServmgrError {
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => ServmgrError::RconAuthentication(source),
+            _ => ServmgrError::RconConnection(source),
+        }
+    }
+
+    pub fn rcon_command(command: &str) -> impl FnOnce(io::Error) -> ServmgrError + '_ {
+        move |source| ServmgrError::RconCommand { command: String::from(command), source }
+    }
+
+    /// (e.g.) `fs::read(path).map_err(ServmgrError::filesystem("read", path))` reads as "Failed to read <path>"
+    pub fn filesystem<'a>(action: &'a str, path: &'a Path) -> impl FnOnce(io::Error) -> ServmgrError + 'a {
+        move |source| ServmgrError::Filesystem { action: String::from(action), path: path.to_path_buf(), source }
+    }
+
+    pub fn archive(action: &str) -> impl FnOnce(ArchiveError) -> ServmgrError + '_ {
+        move |source| ServmgrError::Archive { action: String::from(action), source }
+    }
+
+    pub fn process(program: &str) -> impl FnOnce(io::Error) -> ServmgrError + '_ {
+        move |source| ServmgrError::Process { program: String::from(program), source }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ServmgrError::Failed(_) => 1,
+            ServmgrError::Config(_) => 3,
+            ServmgrError::RconConnection(_) => 4,
+            ServmgrError::RconAuthentication(_) => 5,
+            ServmgrError::RconCommand { .. } => 6,
+            ServmgrError::Filesystem { .. } => 7,
+            ServmgrError::Archive { .. } => 8,
+            ServmgrError::Process { .. } => 9,
+            ServmgrError::Invalid(_) => 10,
+        }
+    }
+
+    /// The error followed by its innermost cause (e.g.) "Failed to read /path (Permission denied)"
+    pub fn summary(&self) -> String {
+        let mut cause = self.source();
+        while let Some(next_cause) = cause.and_then(|cause| cause.source()) { cause = Some(next_cause); }
+        match cause {
+            Some(cause) => format!("{} ({})", self, cause),
+            None => self.to_string(),
+        }
+    }
+
+    /// Prints the error, with every cause on its own line when `verbose`
+    pub fn report(&self, verbose: bool) {
+        if !verbose {
+            eprintln!("{}: {}", style("[ERROR]").red(), self.summary());
+            return;
+        }
+
+        eprintln!("{}: {}", style("[ERROR]").red(), self);
+        let mut cause = self.source();
+        while let Some(current_cause) = cause {
+            eprintln!("{}", style(format!("=> caused by: {}", current_cause)).dim());
+            cause = current_cause.source();
+        }
+        eprintln!("{}", style(format!("=> exit code {}", self.exit_code())).dim());
+    }
+}
+
+impl fmt::Display for ServmgrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServmgrError::Failed(message) | ServmgrError::Config(message) | ServmgrError::Invalid(message) => write!(f, "{}", message),
+            ServmgrError::RconConnection(_) => write!(f, "Failed to connect to the RCON interface"),
+            ServmgrError::RconAuthentication(_) => write!(f, "The RCON interface rejected the password"),
+            ServmgrError::RconCommand { command, .. } => write!(f, "RCON command '{}' failed", command),
+            ServmgrError::Filesystem { action, path, .. } => write!(f, "Failed to {} {}", action, path.display()),
+            ServmgrError::Archive { action, .. } => write!(f, "Failed to {}", action),
+            ServmgrError::Process { program, .. } => write!(f, "Failed to run {}", program),
+        }
+    }
+}
+
+impl Error for ServmgrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ServmgrError::Failed(_) | ServmgrError::Config(_) | ServmgrError::Invalid(_) => None,
+            ServmgrError::RconConnection(source) | ServmgrError::RconAuthentication(source) => Some(source),
+            ServmgrError::RconCommand { source, .. } | ServmgrError::Filesystem { source, .. } | ServmgrError::Process { source, .. } => Some(source),
+            ServmgrError::Archive { source, .. } => Some(source),
+        }
+    }
+}
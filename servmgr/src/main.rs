@@ -1,6 +1,38 @@
+mod archive;
+mod backup_activity;
+mod backup_catalog;
 mod backup_controller;
+mod backup_daemon;
+mod backup_inspector;
+mod backup_manifest;
+mod backup_notifications;
+mod backup_repository;
+mod backup_restore;
+mod backup_retention;
+mod backup_schedule;
+mod backup_state;
+mod backup_verifier;
+mod config;
+mod crash_history;
+mod disk_usage;
+mod error;
+mod nbt;
+mod rcon;
+mod rcon_console;
+mod rcon_exec;
+mod rcon_password;
+mod restart_schedule;
+mod save_guard;
 mod server_activator;
+mod server_log;
+mod server_ping;
+mod server_properties;
+mod server_status;
+mod server_supervisor;
+mod text_component;
+mod world_manager;
 
+use std::path::PathBuf;
 use clap::{Parser, Subcommand};
 use console::style;
 
@@ -9,19 +41,108 @@ use console::style;
 struct Args {
     #[command(subcommand)]
     command: Commands,
+
+    /// Prints every cause of an error instead of only the innermost one, along with the exit code
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Config file to use instead of $SERVMGR_CONFIG or /home/mcadmin/minecraft_server/servmgr.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Starts the minecraft server and attaches your shell to a tmux session dashboard for server monitoring
+    /// Starts the minecraft server in the background under servmgr's supervisor and attaches your shell to its console
     Start {
-        /// Starts the tmux session containing the server dashboard but doesn't attach it to your shell
+        /// Starts the server but doesn't attach your shell to its console
         #[arg(short, long)]
         detached: bool,
     },
 
-    /// Stops the minecraft server and subsequently the tmux session for server monitoring
-    Stop,
+    /// Stops the minecraft server with `stop`, waiting for it to save the world and exit, players can be counted down
+    /// to it and kicked with a message first
+    Stop {
+        /// How long the server gets to exit before it is sent SIGTERM (defaults to server.stop_timeout in servmgr.toml)
+        #[arg(short, long, value_parser = backup_schedule::parse_duration)]
+        timeout: Option<chrono::Duration>,
+
+        /// Counts players down for this long (e.g.) 5m with a title and the action bar before stopping, Ctrl-C cancels
+        #[arg(short, long, value_parser = backup_schedule::parse_duration)]
+        delay: Option<chrono::Duration>,
+
+        /// What players are kicked with (defaults to "The server is shutting down" with --delay or --backup-first)
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Backs up the world before the stop, the server keeps running if the backup fails
+        #[arg(short, long)]
+        backup_first: bool,
+
+        /// RCON password for warning and kicking players, visible to other users while servmgr runs (defaults to
+        /// $SERVMGR_RCON_PASSWORD, rcon.password_file in servmgr.toml or rcon.password in server.properties, and is asked
+        /// for if none is set)
+        #[arg(short, long)]
+        password_rcon: Option<String>,
+    },
+
+    /// Stops the minecraft server and starts it again, or starts it if it isn't running, with --in the restart is
+    /// scheduled and players are warned in chat as it comes closer
+    Restart {
+        /// How long the server gets to exit before it is sent SIGTERM (defaults to server.stop_timeout in servmgr.toml)
+        #[arg(short, long, value_parser = backup_schedule::parse_duration)]
+        timeout: Option<chrono::Duration>,
+
+        /// Restarts the server after this long (e.g.) 10m instead of now, in place of any restart scheduled already
+        #[arg(long = "in", value_parser = backup_schedule::parse_duration, conflicts_with = "timeout")]
+        delay: Option<chrono::Duration>,
+
+        /// Backs up the world right before the scheduled restart stops the server
+        #[arg(short, long, requires = "delay")]
+        backup: bool,
+
+        /// Cancels the scheduled restart (a daily one from restart.times in servmgr.toml only for today)
+        #[arg(short, long, conflicts_with_all = ["timeout", "delay"])]
+        cancel: bool,
+    },
+
+    /// Checks the minecraft server's process, RCON interface, server list ping and backups, exits with 1 if any check
+    /// fails so it works as a Docker HEALTHCHECK
+    Status {
+        /// Also fail when the last successful backup is older than this (e.g.) 26h
+        #[arg(long, value_parser = backup_schedule::parse_duration)]
+        max_backup_age: Option<chrono::Duration>,
+
+        /// Leaves out the RCON check (the server logs every RCON session) and the disk usage, for the Docker HEALTHCHECK
+        #[arg(short, long)]
+        quick: bool,
+
+        /// Print the status as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Lists the times the minecraft server crashed under the supervisor, newest first, with the likely cause of each
+    Crashes {
+        /// How many crashes to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+
+        /// Also print the console output leading up to each crash
+        #[arg(short, long)]
+        log: bool,
+
+        /// Print the crashes as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Attaches your shell to the console of the minecraft server started with `start`, Ctrl-D detaches
+    Attach,
+
+    /// Runs the minecraft server under the supervisor in the foreground until it stops or servmgr receives SIGINT or
+    /// SIGTERM (which stop the server gracefully), `start` runs this in the background
+    Supervise,
 
     /// Creates a backup of the server
     Backup {
@@ -29,88 +150,445 @@ enum Commands {
         action: BackupCommands,
     },
 
+    /// Opens an interactive console for sending commands to the server over RCON (works without attaching to the server console)
+    Console {
+        /// RCON password for the server, visible to other users while servmgr runs (defaults to $SERVMGR_RCON_PASSWORD,
+        /// rcon.password_file in servmgr.toml or rcon.password in server.properties, and is asked for if none is set)
+        #[arg(short, long)]
+        password_rcon: Option<String>,
+    },
+
+    /// Sends one or more commands to the server over RCON and prints their responses
+    Exec {
+        /// Commands to send, each one quoted as a single argument (e.g.) servmgr exec "time set day" "weather clear"
+        commands: Vec<String>,
+
+        /// Also read commands from a file, one per line ("-" reads from stdin)
+        #[arg(short, long)]
+        file: Option<String>,
+
+        /// Print the results as JSON with per-command status, duration and response
+        #[arg(short, long)]
+        json: bool,
+
+        /// RCON password for the server, visible to other users while servmgr runs (defaults to $SERVMGR_RCON_PASSWORD,
+        /// rcon.password_file in servmgr.toml or rcon.password in server.properties, and is asked for if none is set)
+        #[arg(short, long)]
+        password_rcon: Option<String>,
+    },
+
     /// Manages worlds on the server (creating new worlds, selecting active worlds, deleting worlds)
-    Worlds,
+    Worlds {
+        #[command(subcommand)]
+        action: WorldsCommands,
+    },
+
+    /// Shows, checks or creates servmgr.toml, where servmgr finds the server, its worlds and backups
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Prints every setting with its value and where it came from (default, servmgr.toml or an environment variable)
+    Show {
+        /// Print the settings as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Checks servmgr.toml along with the backup schedules and notifications files, exits with an error if any is invalid
+    Validate,
+
+    /// Writes a servmgr.toml with every setting at its default
+    Init {
+        /// Overwrites an existing config file
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorldsCommands {
+    /// Lists every managed world with its size, last played time and game version (the selected world is marked with *)
+    List,
+
+    /// Creates a new world which the server generates the first time it loads it
+    Create {
+        /// Name of the world (letters, numbers, '-', '_' and '.')
+        name: String,
+
+        /// Seed used to generate the world (random if not given)
+        #[arg(short, long)]
+        seed: Option<String>,
+
+        /// Level type used to generate the world (e.g.) normal, flat, large_biomes, amplified
+        #[arg(short, long)]
+        level_type: Option<String>,
+
+        /// Generator settings as JSON (e.g.) for customizing flat worlds
+        #[arg(short, long)]
+        generator_settings: Option<String>,
+
+        /// Selects the world after creating it
+        #[arg(long)]
+        select: bool,
+    },
+
+    /// Selects the world the server loads on its next start (the server must be stopped)
+    Select {
+        /// Name of the world
+        name: String,
+    },
+
+    /// Permanently deletes a world
+    Delete {
+        /// Name of the world
+        name: String,
+
+        /// Deletes the world without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Renames a world
+    Rename {
+        /// Current name of the world
+        name: String,
+
+        /// New name of the world
+        new_name: String,
+    },
+
+    /// Shows the size on disk, seed, last played time and game version of a world
+    Info {
+        /// Name of the world
+        name: String,
+    },
 }
 
 #[derive(Subcommand)]
 enum BackupCommands {
     /// Create a manual one time backup of the world loaded onto the server in its current state
     Man {
-        /// RCON password for the server
+        /// RCON password for the server, visible to other users while servmgr runs (defaults to $SERVMGR_RCON_PASSWORD,
+        /// rcon.password_file in servmgr.toml or rcon.password in server.properties, and is asked for if none is set)
         #[arg(short, long)]
-        password_rcon: String,
+        password_rcon: Option<String>,
+
+        /// Archive format to write the backup in
+        #[arg(long, value_enum, default_value = "tar-gz")]
+        format: archive::ArchiveFormat,
+
+        /// Compression level, 0-9 for tar-gz, tar-xz and zip or 1-22 for tar-zst (defaults to 6, or 3 for tar-zst)
+        #[arg(long)]
+        level: Option<u32>,
+
+        /// Stores the backup in the deduplicating repository under the backups directory instead of a full archive,
+        /// only region chunks and files that changed since earlier backups take up space
+        #[arg(short, long, conflicts_with_all = ["format", "level"])]
+        incremental: bool,
+
+        /// Skips pruning old backups with the retention policy after the backup succeeds
+        #[arg(long)]
+        no_prune: bool,
+
+        /// How long the server may take to save the world before the backup is abandoned (e.g.) 30s or 10m
+        #[arg(long, default_value = "5m", value_parser = backup_schedule::parse_duration)]
+        save_timeout: chrono::Duration,
+
+        /// Doesn't tell players about the backup in chat, whatever backup_notifications.toml says
+        #[arg(short, long)]
+        quiet: bool,
+
+        #[command(flatten)]
+        retention: backup_retention::RetentionPolicy,
     },
 
-    /// Create an automatic backup of the world loaded onto the server after a set period of time (default is 1 hour),
-    /// each argument is respective of itself (e.g.) "-s 5 -m 10 -h 3 -D 4" is 4 days, 3 hours, 10 minutes, and 5 seconds between each backup
+    /// Create automatic backups of the world loaded onto the server on the schedules in backup_schedules.toml, runs the
+    /// backup daemon in the foreground or detached from the terminal
     Auto {
-        /// RCON password for the server
+        /// RCON password for the server, visible to other users while servmgr runs (defaults to $SERVMGR_RCON_PASSWORD,
+        /// rcon.password_file in servmgr.toml or rcon.password in server.properties, and is asked for if none is set)
         #[arg(short, long)]
-        password_rcon: String,
+        password_rcon: Option<String>,
 
-        /// Starts the backup schedule in detached mode (doesn't attach to the tmux session)
+        /// Starts the backup daemon in the background, logging to servmgr_backup_daemon.log in the server directory
         #[arg(short, long)]
         detached: bool,
 
-        /// Seconds between each backup
-        #[arg(short, long, default_value = "30")]
-        seconds: i32,
+        /// Backs up on this single schedule instead of the schedules file, a cron expression (e.g.) "0 */2 * * *" or a
+        /// calendar spec (e.g.) "weekdays every 2h between 08:00 and 23:00"
+        #[arg(short, long)]
+        when: Option<String>,
+    },
+
+    /// Runs the backup schedule in the foreground until it receives SIGINT or SIGTERM, an in-flight backup is finished
+    /// and saving is re-enabled before it exits
+    Daemon {
+        /// RCON password for the server, visible to other users while servmgr runs (defaults to $SERVMGR_RCON_PASSWORD,
+        /// rcon.password_file in servmgr.toml or rcon.password in server.properties, and is asked for if none is set)
+        #[arg(short, long)]
+        password_rcon: Option<String>,
 
-        /// Minutes between each backup
-        #[arg(short, long, default_value = "0")]
-        minutes: i32,
+        /// Backs up on this single schedule instead of the schedules file
+        #[arg(short, long)]
+        when: Option<String>,
+    },
 
-        /// Hours between each backup
-        #[arg(short = 'H', long, default_value = "0")]
-        hours: i32,
+    /// Stops the automatic backup schedule, waiting for an in-flight backup to finish
+    AutoStop,
 
-        /// Days between each backup
-        #[arg(short = 'D', long, default_value = "0")]
-        days: i32,
+    /// Shows whether the backup daemon is running, what it is doing and when it backs up next
+    AutoStatus {
+        /// Print the status as JSON
+        #[arg(short, long)]
+        json: bool,
     },
 
-    /// Stops the automatic backup schedule
-    AutoStop {
-        /// RCON password for the server
+    /// Shows the last backup attempt, success and failure and the next scheduled run, exits with an error when the last
+    /// successful backup is older than --max-age
+    Status {
+        /// How old the last successful backup may be before the status is an error (e.g.) 90m, 25h or 7d
+        #[arg(short, long, default_value = "25h", value_parser = backup_schedule::parse_duration)]
+        max_age: chrono::Duration,
+
+        /// Print the status as JSON
         #[arg(short, long)]
-        password_rcon: String
+        json: bool,
+    },
+
+    /// Works with the backup schedules
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleCommands,
+    },
+
+    /// Restores a backup over the selected world, keeping the current world as a pre-restore snapshot in the server
+    /// directory's .restore/ (only the newest --keep-snapshots of them are kept)
+    Restore {
+        /// File name of the backup to restore (with or without its extension), or "latest"
+        #[arg(required_unless_present = "at", conflicts_with = "at")]
+        name: Option<String>,
+
+        /// Restores the newest backup taken at or before this local time (e.g.) "2026-10-01 04:00"
+        #[arg(short, long)]
+        at: Option<String>,
+
+        /// Stops the server if it is running instead of refusing to restore
+        #[arg(short, long)]
+        stop: bool,
+
+        /// Number of pre-restore snapshots to keep in .restore/, older ones are deleted (0 keeps none, not even this one)
+        #[arg(short, long, default_value = "3")]
+        keep_snapshots: usize,
+    },
+
+    /// Lists every backup with its size, age, world and integrity
+    List {
+        /// Print the backups as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Shows the contents, dimensions and level.dat metadata of a backup without extracting it
+    Show {
+        /// File name of the backup (with or without its extension), or "latest"
+        name: String,
+
+        /// Print the backup details as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Deletes the backups the retention policy doesn't keep (the newest and pinned backups are always kept)
+    Prune {
+        /// Only print which backups would be kept and which would be deleted
+        #[arg(short, long)]
+        dry_run: bool,
+
+        /// Only prunes the backups taken by this schedule, "manual" for backups taken with `backup man`
+        #[arg(short, long)]
+        schedule: Option<String>,
+
+        #[command(flatten)]
+        retention: backup_retention::RetentionPolicy,
+    },
+
+    /// Re-reads backups and checks them against their manifests, exits with an error if any backup is corrupt
+    Verify {
+        /// File name of the backup (with or without its extension), or "latest"
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+
+        /// Verifies every backup
+        #[arg(short, long)]
+        all: bool,
+    },
+
+    /// Deletes chunks in the incremental backup repository that no backup references anymore (prune does this on its own)
+    Gc {
+        /// Only print how much would be removed
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Pins a backup so it is never pruned
+    Pin {
+        /// File name of the backup (with or without its extension), or "latest"
+        name: String,
+    },
+
+    /// Unpins a backup so the retention policy applies to it again
+    Unpin {
+        /// File name of the backup (with or without its extension), or "latest"
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommands {
+    /// Prints when the next backups run, soonest first
+    Next {
+        /// How many runs to print
+        #[arg(short = 'n', long, default_value = "10")]
+        count: usize,
+
+        /// Only prints the runs of this schedule from the schedules file
+        #[arg(long, conflicts_with = "when")]
+        name: Option<String>,
+
+        /// Prints the runs of this schedule spec instead of the schedules file
+        #[arg(short, long)]
+        when: Option<String>,
     },
 }
 
 fn main() {
     let args = Args::parse();
 
-    match args.command {
-        Commands::Start {detached} => {
-            server_activator::start_minecraft_server(&detached);
-        },
-        Commands::Stop {..} => {
-            server_activator::stop_minecraft_server();
+    // init works without a config, and over a broken one with --force
+    let result = match args.command {
+        Commands::Config {action: ConfigCommands::Init {force}} => config::init_config(args.config.as_deref(), force),
+        command => config::init(args.config.as_deref()).and_then(|_| {
+            if repairs_stale_save_off(&command) { save_guard::repair_stale_save_off(); }
+            run(command)
+        }),
+    };
+
+    if let Err(err) = result {
+        err.report(args.verbose);
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Whether the command takes or restores a backup or starts the server, the ones that should find saving turned back
+/// on, everything else (`status` above all, which the Docker HEALTHCHECK runs every minute) leaves the server alone
+fn repairs_stale_save_off(command: &Commands) -> bool {
+    match command {
+        Commands::Start {..} | Commands::Restart {..} | Commands::Supervise | Commands::Stop {backup_first: true, ..} => true,
+        Commands::Backup {action} => matches!(action,
+            BackupCommands::Man {..} | BackupCommands::Auto {..} | BackupCommands::Daemon {..} | BackupCommands::Restore {..}),
+        _ => false,
+    }
+}
+
+fn run(command: Commands) -> Result<(), error::ServmgrError> {
+    match command {
+        Commands::Start {detached} => server_activator::start_minecraft_server(detached),
+        Commands::Stop {timeout, delay, message, backup_first, password_rcon} => {
+            let options = server_activator::StopOptions {timeout, delay, message, backup_first, rcon_password: password_rcon};
+            server_activator::stop_minecraft_server(&options)
         },
+        Commands::Restart {cancel: true, ..} => server_activator::cancel_scheduled_restart(),
+        Commands::Restart {delay: Some(delay), backup, ..} => server_activator::schedule_restart(delay, backup),
+        Commands::Restart {timeout, ..} => server_activator::restart_minecraft_server(timeout),
+        Commands::Status {max_backup_age, quick, json} => server_status::show_server_status(max_backup_age, quick, json),
+        Commands::Crashes {count, log, json} => crash_history::show_crash_history(count, log, json),
+        Commands::Attach => server_activator::attach_console(),
+        Commands::Supervise => server_supervisor::run_server_supervisor(),
         Commands::Backup {action} => {
             match action {
-                BackupCommands::Man {password_rcon} => {
+                BackupCommands::Man {password_rcon, format, level, incremental, no_prune, save_timeout, quiet, retention} => {
                     let current_time = chrono::Local::now();
-                    let success: bool = backup_controller::backup_minecraft_server(&password_rcon);
-                    if success {
-                        println!("{}", style(format!("[INFO] Backed up Minecraft server at {}", current_time.format("%Y-%m-%d %H:%M:%S"))).cyan().to_string());
-                    } else {
-                        println!("{}", style(format!("[ERROR] Failed to back up Minecraft server at {}", current_time.format("%Y-%m-%d %H:%M:%S"))).red().to_string());
+                    let storage = if incremental { backup_catalog::BackupStorage::Repository } else { backup_catalog::BackupStorage::Archive(format) };
+                    let options = backup_controller::BackupOptions {storage, level, schedule: None, save_timeout,
+                        notifications: quiet.then_some(backup_notifications::Verbosity::None)};
+                    let rcon_password = rcon_password::resolve_rcon_password(password_rcon.as_deref())?;
+                    save_guard::restore_saving_on_signal()?;
+                    backup_controller::backup_minecraft_server(&rcon_password.password, &options)?;
+                    println!("{}", style(format!("[INFO] Backed up Minecraft server at {}", current_time.format("%Y-%m-%d %H:%M:%S"))).cyan());
+                    if no_prune { Ok(()) } else { backup_retention::prune_backups(&retention, backup_retention::PruneScope::Manual, false) }
+                },
+                BackupCommands::Auto {password_rcon, detached, when} => {
+                    let rcon_password = rcon_password::resolve_rcon_password(password_rcon.as_deref())?;
+                    backup_daemon::start_backup_daemon(&rcon_password, when.as_deref(), detached)
+                },
+                BackupCommands::Daemon {password_rcon, when} => {
+                    let rcon_password = rcon_password::resolve_rcon_password(password_rcon.as_deref())?;
+                    backup_daemon::run_backup_daemon(&rcon_password.password, when.as_deref())
+                },
+                BackupCommands::AutoStop => backup_daemon::stop_backup_daemon(),
+                BackupCommands::AutoStatus {json} => backup_daemon::show_backup_daemon_status(json),
+                BackupCommands::Status {max_age, json} => backup_state::show_backup_status(max_age, json),
+                BackupCommands::Schedule {action} => {
+                    match action {
+                        ScheduleCommands::Next {count, name, when} => backup_schedule::print_next_runs(name.as_deref(), when.as_deref(), count),
                     }
                 },
-                BackupCommands::Auto {password_rcon, detached, seconds, minutes, hours, days} => {
-                    let interval = std::time::Duration::from_secs(
-                        seconds as u64 + minutes as u64 * 60 + hours as u64 * 3600 + days as u64 * 86400);
-                    backup_controller::start_schedule_minecraft_server_backups(&password_rcon, &interval, &detached);
+                BackupCommands::Restore {name, at, stop, keep_snapshots} => {
+                    let target = match (&name, &at) {
+                        (_, Some(at)) => backup_restore::RestoreTarget::At(at),
+                        (Some(name), None) => backup_restore::RestoreTarget::Named(name),
+                        (None, None) => unreachable!("clap requires a backup name or --at"),
+                    };
+
+                    backup_restore::restore_minecraft_server_backup(target, stop, keep_snapshots)
                 },
-                BackupCommands::AutoStop {password_rcon} => {
-                    backup_controller::stop_scheduled_minecraft_server_backups(&password_rcon);
+                BackupCommands::List {json} => backup_inspector::list_minecraft_server_backups(json),
+                BackupCommands::Show {name, json} => backup_inspector::show_minecraft_server_backup(&name, json),
+                BackupCommands::Prune {dry_run, schedule, retention} => {
+                    let scope = match schedule.as_deref() {
+                        None => backup_retention::PruneScope::All,
+                        Some("manual") => backup_retention::PruneScope::Manual,
+                        Some(schedule) => backup_retention::PruneScope::Schedule(schedule),
+                    };
+                    backup_retention::prune_backups(&retention, scope, dry_run)
                 },
+                BackupCommands::Verify {name, all: _} => backup_verifier::verify_minecraft_server_backups(name.as_deref()),
+                BackupCommands::Gc {dry_run} => backup_retention::collect_repository_garbage(dry_run),
+                BackupCommands::Pin {name} => backup_retention::set_backup_pinned(&name, true),
+                BackupCommands::Unpin {name} => backup_retention::set_backup_pinned(&name, false),
             }
         },
-        Commands::Worlds {..} => {
-            println!("Opening worlds manager...");
+        Commands::Console {password_rcon} => {
+            let rcon_password = rcon_password::resolve_rcon_password(password_rcon.as_deref())?;
+            rcon_console::open_rcon_console(&rcon_password.password)
+        },
+        Commands::Exec {commands, file, json, password_rcon} => {
+            let rcon_password = rcon_password::resolve_rcon_password(password_rcon.as_deref())?;
+            rcon_exec::execute_rcon_commands(&rcon_password.password, &commands, file.as_deref(), json)
+        },
+        Commands::Worlds {action} => {
+            match action {
+                WorldsCommands::List => world_manager::list_worlds(),
+                WorldsCommands::Create {name, seed, level_type, generator_settings, select} => {
+                    world_manager::create_world(&name, seed.as_deref(), level_type.as_deref(), generator_settings.as_deref(), select)
+                },
+                WorldsCommands::Select {name} => world_manager::select_world(&name),
+                WorldsCommands::Delete {name, yes} => world_manager::delete_world(&name, yes),
+                WorldsCommands::Rename {name, new_name} => world_manager::rename_world(&name, &new_name),
+                WorldsCommands::Info {name} => world_manager::show_world_info(&name),
+            }
+        },
+        Commands::Config {action} => {
+            match action {
+                ConfigCommands::Show {json} => config::show_config(json),
+                ConfigCommands::Validate => config::validate_config(),
+                ConfigCommands::Init {..} => unreachable!("init runs before the config is loaded"),
+            }
         },
     }
 }
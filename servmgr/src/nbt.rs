@@ -0,0 +1,137 @@
+use std::{collections::HashMap, io::{self, Read}};
+use flate2::read::GzDecoder;
+
+/// A decoded NBT tag (https://minecraft.wiki/w/NBT_format)
+#[allow(dead_code)] // every tag type has to be decoded to walk the file, even the ones servmgr never inspects
+pub enum NbtTag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<NbtTag>),
+    Compound(HashMap<String, NbtTag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl NbtTag {
+    /// Follows a path of compound keys (e.g.) `["Data", "Version", "Name"]`
+    pub fn get_path(&self, path: &[&str]) -> Option<&NbtTag> {
+        path.iter().try_fold(self, |tag, key| match tag {
+            NbtTag::Compound(fields) => fields.get(*key),
+            _ => None,
+        })
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            NbtTag::Byte(value) => Some(*value as i64),
+            NbtTag::Short(value) => Some(*value as i64),
+            NbtTag::Int(value) => Some(*value as i64),
+            NbtTag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            NbtTag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a gzip compressed NBT file such as `level.dat` and returns its root compound
+pub fn read_gzip_nbt(reader: impl Read) -> io::Result<NbtTag> {
+    let mut decoder = GzDecoder::new(reader);
+    let tag_type = read_u8(&mut decoder)?;
+    if tag_type != 10 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "NBT root tag is not a compound"));
+    }
+
+    read_string(&mut decoder)?; // the root's name is always empty
+    read_payload(&mut decoder, tag_type, 0)
+}
+
+const MAX_NESTING_DEPTH: usize = 512;
+const MAX_ARRAY_LENGTH: usize = 16 * 1024 * 1024; // keeps a corrupt length from allocating gigabytes
+
+fn read_payload(reader: &mut impl Read, tag_type: u8, depth: usize) -> io::Result<NbtTag> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "NBT data is nested too deeply"));
+    }
+
+    Ok(match tag_type {
+        1 => NbtTag::Byte(read_u8(reader)? as i8),
+        2 => NbtTag::Short(i16::from_be_bytes(read_array(reader)?)),
+        3 => NbtTag::Int(i32::from_be_bytes(read_array(reader)?)),
+        4 => NbtTag::Long(i64::from_be_bytes(read_array(reader)?)),
+        5 => NbtTag::Float(f32::from_be_bytes(read_array(reader)?)),
+        6 => NbtTag::Double(f64::from_be_bytes(read_array(reader)?)),
+        7 => {
+            let length = read_length(reader)?;
+            let mut bytes = vec![0u8; length];
+            reader.read_exact(&mut bytes)?;
+            NbtTag::ByteArray(bytes.into_iter().map(|byte| byte as i8).collect())
+        },
+        8 => NbtTag::String(read_string(reader)?),
+        9 => {
+            let element_type = read_u8(reader)?;
+            let length = read_length(reader)?;
+            let mut elements = Vec::new();
+            for _ in 0..length { elements.push(read_payload(reader, element_type, depth + 1)?); }
+            NbtTag::List(elements)
+        },
+        10 => {
+            let mut fields = HashMap::new();
+            loop {
+                let field_type = read_u8(reader)?;
+                if field_type == 0 { break; }
+                let name = read_string(reader)?;
+                fields.insert(name, read_payload(reader, field_type, depth + 1)?);
+            }
+            NbtTag::Compound(fields)
+        },
+        11 => {
+            let length = read_length(reader)?;
+            let mut values = Vec::new();
+            for _ in 0..length { values.push(i32::from_be_bytes(read_array(reader)?)); }
+            NbtTag::IntArray(values)
+        },
+        12 => {
+            let length = read_length(reader)?;
+            let mut values = Vec::new();
+            for _ in 0..length { values.push(i64::from_be_bytes(read_array(reader)?)); }
+            NbtTag::LongArray(values)
+        },
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown NBT tag type {}", tag_type))),
+    })
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    Ok(read_array::<1>(reader)?[0])
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_length(reader: &mut impl Read) -> io::Result<usize> {
+    let length = i32::from_be_bytes(read_array(reader)?);
+    usize::try_from(length).ok()
+        .filter(|length| *length <= MAX_ARRAY_LENGTH)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid NBT length {}", length)))
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let length = u16::from_be_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0u8; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned()) // java's modified UTF-8 only differs for nulls and surrogates
+}
@@ -0,0 +1,199 @@
+use std::{io::{self, Read, Write}, net::{TcpStream, ToSocketAddrs}, time::Duration};
+use crate::{config, rcon_password};
+use crate::server_properties::ServerProperties;
+
+// packet types from the Source RCON protocol (https://developer.valvesoftware.com/wiki/Source_RCON_Protocol)
+const SERVERDATA_AUTH: i32 = 3;
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+// the minecraft server answers packets of an unknown type with a single response carrying the same request id,
+// so one is sent after every command to mark where a (possibly multi-packet) response ends
+const END_OF_RESPONSE_PACKET_TYPE: i32 = 200;
+
+const MAX_COMMAND_LENGTH: usize = 1446;
+const MAX_PACKET_LENGTH: i32 = 4096 + 10;
+const DEFAULT_RCON_PORT: u16 = 25575;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where and how to reach the server's RCON interface
+#[derive(Clone)]
+pub struct RconConfig {
+    pub host: String,
+    pub port: u16,
+    pub password: String,
+}
+
+impl RconConfig {
+    /// Reads `server-ip`, `rcon.port` and `rcon.password` from the server's `server.properties`
+    pub fn from_server_properties(properties: &ServerProperties) -> io::Result<RconConfig> {
+        if properties.get("enable-rcon").as_deref() != Some("true") {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "RCON is disabled (enable-rcon=false in server.properties)"));
+        }
+
+        let host = properties.get("server-ip")
+            .filter(|ip| !ip.trim().is_empty())
+            .unwrap_or_else(|| String::from("127.0.0.1"));
+        let port = match properties.get("rcon.port") {
+            Some(port) => port.trim().parse::<u16>()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid rcon.port '{}' in server.properties", port)))?,
+            None => DEFAULT_RCON_PORT,
+        };
+        let password = properties.get("rcon.password").unwrap_or_default();
+
+        Ok(RconConfig { host, port, password })
+    }
+
+    /// Same as `from_server_properties` but with the host and port in servmgr.toml taking precedence, and the password
+    /// given or else the one `rcon_password::find_rcon_password` finds
+    pub fn load(rcon_password: Option<&str>) -> io::Result<RconConfig> {
+        let properties = ServerProperties::load_default()?;
+        let mut rcon_config = RconConfig::from_server_properties(&properties)?;
+        let servmgr_config = config::get();
+        if let Some(host) = &servmgr_config.rcon_host { rcon_config.host = host.clone(); }
+        if let Some(port) = servmgr_config.rcon_port { rcon_config.port = port; }
+        match rcon_password {
+            Some(rcon_password) => rcon_config.password = rcon_password.to_string(),
+            None => if let Some(found) = rcon_password::find_rcon_password(&properties)? { rcon_config.password = found.password; },
+        }
+        Ok(rcon_config)
+    }
+}
+
+/// A single authenticated RCON connection, reused for every command sent through it
+pub struct RconClient {
+    stream: TcpStream,
+    next_request_id: i32,
+}
+
+impl RconClient {
+    pub fn connect(config: &RconConfig) -> io::Result<RconClient> {
+        let address = (config.host.as_str(), config.port).to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Could not resolve RCON host '{}'", config.host)))?;
+
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.set_nodelay(true)?;
+
+        let mut client = RconClient { stream, next_request_id: 1 };
+        client.login(&config.password)?;
+        Ok(client)
+    }
+
+    fn login(&mut self, password: &str) -> io::Result<()> {
+        let request_id = self.allocate_request_id();
+        self.send_packet(request_id, SERVERDATA_AUTH, password)?;
+
+        loop {
+            let (response_id, packet_type, _) = self.read_packet()?;
+            if packet_type != SERVERDATA_AUTH_RESPONSE { continue; } // some servers send an empty RESPONSE_VALUE first
+
+            if response_id == -1 {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "RCON authentication failed (wrong password)"));
+            }
+            if response_id != request_id {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "RCON authentication response had an unexpected request id"));
+            }
+            return Ok(());
+        }
+    }
+
+    /// Sends a command and returns the server's full response, reassembled from as many packets as it was split into
+    pub fn command(&mut self, command: &str) -> io::Result<String> {
+        if command.len() > MAX_COMMAND_LENGTH {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("RCON commands are limited to {} bytes", MAX_COMMAND_LENGTH)));
+        }
+
+        let request_id = self.allocate_request_id();
+        let end_marker_id = self.allocate_request_id();
+        self.send_packet(request_id, SERVERDATA_EXECCOMMAND, command)?;
+        self.send_packet(end_marker_id, END_OF_RESPONSE_PACKET_TYPE, "")?;
+
+        // bodies are joined as raw bytes since the server may split a response in the middle of a UTF-8 character
+        let mut response = Vec::new();
+        loop {
+            let (response_id, packet_type, body) = self.read_packet()?;
+            if response_id == end_marker_id { break; }
+            if response_id == -1 {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "RCON session is not authenticated"));
+            }
+            if response_id == request_id && packet_type == SERVERDATA_RESPONSE_VALUE {
+                response.extend_from_slice(&body);
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&response).into_owned())
+    }
+
+    fn allocate_request_id(&mut self) -> i32 {
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.checked_add(1).unwrap_or(1);
+        request_id
+    }
+
+    fn send_packet(&mut self, request_id: i32, packet_type: i32, body: &str) -> io::Result<()> {
+        let body = body.as_bytes();
+        let length = (4 + 4 + body.len() + 2) as i32;
+
+        let mut packet = Vec::with_capacity(length as usize + 4);
+        packet.extend_from_slice(&length.to_le_bytes());
+        packet.extend_from_slice(&request_id.to_le_bytes());
+        packet.extend_from_slice(&packet_type.to_le_bytes());
+        packet.extend_from_slice(body);
+        packet.extend_from_slice(&[0, 0]);
+
+        self.stream.write_all(&packet)
+    }
+
+    fn read_packet(&mut self) -> io::Result<(i32, i32, Vec<u8>)> {
+        let mut length_bytes = [0u8; 4];
+        self.stream.read_exact(&mut length_bytes)?;
+        let length = i32::from_le_bytes(length_bytes);
+        if !(10..=MAX_PACKET_LENGTH).contains(&length) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Malformed RCON packet length: {}", length)));
+        }
+
+        let mut payload = vec![0u8; length as usize];
+        self.stream.read_exact(&mut payload)?;
+
+        let request_id = i32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        let packet_type = i32::from_le_bytes([payload[4], payload[5], payload[6], payload[7]]);
+        let body = payload[8..payload.len() - 2].to_vec();
+
+        Ok((request_id, packet_type, body))
+    }
+}
+
+/// Opens an RCON session and makes sure the server is actually answering commands, not just accepting connections
+pub fn open_responding_session(rcon_password: Option<&str>) -> io::Result<RconClient> {
+    let mut rcon = RconClient::connect(&RconConfig::load(rcon_password)?)?;
+    rcon.command("list")?;
+    Ok(rcon)
+}
+
+/// Pulls the online player names out of the response to `list`
+/// (e.g.) "There are 2 of a max of 10 players online: Steve, Alex"
+pub fn parse_player_names(list_response: &str) -> Vec<String> {
+    match list_response.split_once(':') {
+        Some((_, names)) => names.split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_names_from_list() {
+        assert_eq!(parse_player_names("There are 2 of a max of 10 players online: Steve, Alex"), ["Steve", "Alex"]);
+        assert!(parse_player_names("There are 0 of a max of 10 players online: ").is_empty());
+        assert!(parse_player_names("Unknown command").is_empty());
+    }
+}
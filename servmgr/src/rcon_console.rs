@@ -0,0 +1,126 @@
+use std::{path::PathBuf, time::{Duration, Instant}};
+use console::style;
+use rustyline::{Context, Editor, Helper, Highlighter, Hinter, Validator};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use crate::rcon::{self, RconClient, RconConfig};
+use crate::text_component;
+use crate::error::ServmgrError;
+
+const HISTORY_FILE_NAME: &str = ".servmgr_console_history";
+/// How stale the player names offered for completion may get before `list` is sent again, checked before each prompt
+/// so the server log isn't filled with a `list` for every command typed
+const PLAYER_NAMES_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+const VANILLA_COMMANDS: &[&str] = &[
+    "advancement", "attribute", "ban", "ban-ip", "banlist", "bossbar", "clear", "clone", "damage", "data", "datapack",
+    "debug", "defaultgamemode", "deop", "dialog", "difficulty", "effect", "enchant", "execute", "experience", "fill",
+    "fillbiome", "forceload", "function", "gamemode", "gamerule", "give", "help", "item", "jfr", "kick", "kill", "list",
+    "locate", "loot", "me", "msg", "op", "pardon", "pardon-ip", "particle", "perf", "place", "playsound", "random",
+    "recipe", "reload", "return", "ride", "rotate", "save-all", "save-off", "save-on", "say", "schedule", "scoreboard",
+    "seed", "setblock", "setidletimeout", "setworldspawn", "spawnpoint", "spectate", "spreadplayers", "stop",
+    "stopsound", "summon", "tag", "team", "teammsg", "teleport", "tell", "tellraw", "test", "tick", "time", "title", "tm",
+    "tp", "transfer", "trigger", "version", "w", "waypoint", "weather", "whitelist", "worldborder", "xp",
+];
+
+const TARGET_SELECTORS: &[&str] = &["@a", "@e", "@n", "@p", "@r", "@s"];
+
+#[derive(Helper, Hinter, Highlighter, Validator)]
+struct ConsoleHelper {
+    player_names: Vec<String>,
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let word_start = line.rfind(' ').map(|index| index + 1).unwrap_or(0);
+        let word = &line[word_start..];
+
+        let candidates: Vec<&str> = if line[..word_start].trim().is_empty() {
+            VANILLA_COMMANDS.to_vec()
+        } else {
+            self.player_names.iter().map(String::as_str).chain(TARGET_SELECTORS.iter().copied()).collect()
+        };
+
+        let matches = candidates.into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| Pair { display: candidate.to_string(), replacement: candidate.to_string() })
+            .collect();
+
+        Ok((word_start, matches))
+    }
+}
+
+fn refresh_player_names(rcon: &mut RconClient, editor: &mut Editor<ConsoleHelper, DefaultHistory>) -> Instant {
+    if let Ok(list_response) = rcon.command("list") { set_player_names(&list_response, editor); }
+    Instant::now()
+}
+
+fn set_player_names(list_response: &str, editor: &mut Editor<ConsoleHelper, DefaultHistory>) {
+    if let Some(helper) = editor.helper_mut() {
+        helper.player_names = rcon::parse_player_names(list_response);
+    }
+}
+
+pub fn open_rcon_console(rcon_password: &str) -> Result<(), ServmgrError> {
+    let config = RconConfig::load(Some(rcon_password)).map_err(|err| ServmgrError::Config(format!("Failed to load the RCON settings ({})", err)))?;
+    let mut rcon = RconClient::connect(&config).map_err(ServmgrError::rcon)?;
+
+    let mut editor: Editor<ConsoleHelper, DefaultHistory> = Editor::new()
+        .map_err(|err| ServmgrError::Failed(format!("Failed to initialize the console ({})", err)))?;
+    editor.set_helper(Some(ConsoleHelper { player_names: Vec::new() }));
+
+    let history_path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME));
+    if let Some(history_path) = &history_path {
+        let _ = editor.load_history(history_path); // no history yet on the first run
+    }
+
+    let mut player_names_refreshed_at = refresh_player_names(&mut rcon, &mut editor);
+    println!("{}", style("Connected to the Minecraft server console over RCON (type 'exit' or press Ctrl-D to leave)").cyan());
+
+    let mut result = Ok(());
+    loop {
+        if player_names_refreshed_at.elapsed() >= PLAYER_NAMES_REFRESH_INTERVAL {
+            player_names_refreshed_at = refresh_player_names(&mut rcon, &mut editor);
+        }
+
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                result = Err(ServmgrError::Failed(format!("Failed to read from the console ({})", err)));
+                break;
+            },
+        };
+
+        let command = line.trim().trim_start_matches('/');
+        if command.is_empty() { continue; }
+        let _ = editor.add_history_entry(line.trim());
+        if command == "exit" || command == "quit" { break; }
+
+        match rcon.command(command) {
+            Ok(response) => {
+                if !response.is_empty() { println!("{}", text_component::render_response(&response)); }
+                // the names are right there already
+                if command == "list" {
+                    set_player_names(&response, &mut editor);
+                    player_names_refreshed_at = Instant::now();
+                }
+            },
+            Err(err) => {
+                result = Err(ServmgrError::rcon_command(command)(err));
+                break;
+            },
+        }
+    }
+
+    if let Some(history_path) = &history_path
+        && let Err(err) = editor.save_history(history_path) {
+        eprintln!("{}: Failed to save console history ({})", style("[ERROR]").red(), err);
+    }
+    result
+}
@@ -0,0 +1,76 @@
+use std::{fs, io::{self, BufRead}, path::Path, time::Instant};
+use console::style;
+use serde_json::json;
+use crate::rcon;
+use crate::error::ServmgrError;
+
+/// Collects the commands to send, from the command line followed by a file (or stdin when the path is `-`), one per line
+fn collect_commands(commands: &[String], file: Option<&str>) -> io::Result<Vec<String>> {
+    let mut collected: Vec<String> = commands.to_vec();
+
+    if let Some(file) = file {
+        let lines: Vec<String> = if file == "-" {
+            io::stdin().lock().lines().collect::<io::Result<_>>()?
+        } else {
+            fs::read_to_string(file)?.lines().map(String::from).collect()
+        };
+        collected.extend(lines);
+    }
+
+    Ok(collected.into_iter()
+        .map(|command| command.trim().trim_start_matches('/').to_string())
+        .filter(|command| !command.is_empty() && !command.starts_with('#'))
+        .collect())
+}
+
+/// Sends each command over one RCON session and prints the responses, fails with the first command that failed
+pub fn execute_rcon_commands(rcon_password: &str, commands: &[String], file: Option<&str>, json_output: bool) -> Result<(), ServmgrError> {
+    let commands = collect_commands(commands, file)
+        .map_err(ServmgrError::filesystem("read commands from", Path::new(file.unwrap_or("-"))))?;
+
+    if commands.is_empty() {
+        return Err(ServmgrError::Invalid(String::from("No commands given")));
+    }
+
+    let mut rcon = match rcon::open_responding_session(Some(rcon_password)) {
+        Ok(rcon) => rcon,
+        Err(err) => {
+            if json_output {
+                println!("{}", json!({"connected": false, "error": err.to_string(), "results": []}));
+            }
+            return Err(ServmgrError::rcon(err));
+        },
+    };
+
+    let mut first_error = None;
+    let mut results = Vec::with_capacity(commands.len());
+    for command in &commands {
+        let start_time = Instant::now();
+        let result = rcon.command(command);
+        let duration_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        match result {
+            Ok(response) => {
+                if json_output {
+                    results.push(json!({"command": command, "status": "ok", "duration_ms": duration_ms, "response": response}));
+                } else if !response.is_empty() {
+                    println!("{}", response);
+                }
+            },
+            Err(err) => {
+                if json_output {
+                    results.push(json!({"command": command, "status": "error", "duration_ms": duration_ms, "error": err.to_string()}));
+                } else {
+                    eprintln!("{}: '{}' failed ({})", style("[ERROR]").red(), command, err);
+                }
+                first_error.get_or_insert(ServmgrError::rcon_command(command)(err));
+            },
+        }
+    }
+
+    if json_output {
+        println!("{}", json!({"connected": true, "results": results}));
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
@@ -0,0 +1,117 @@
+use std::{env, fs, io::{self, BufRead, IsTerminal, Write}, os::unix::fs::PermissionsExt, path::Path};
+use console::style;
+use crate::config;
+use crate::error::ServmgrError;
+use crate::server_properties::ServerProperties;
+
+/// Holds the RCON password in place of `-p`, which leaves it in the shell history and the process list, the backup
+/// daemon hands a password it can't find on its own to its detached process through it
+pub const RCON_PASSWORD_VARIABLE: &str = "SERVMGR_RCON_PASSWORD";
+
+/// Where the RCON password was found, in the order they are tried
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PasswordSource {
+    CommandLine,
+    Environment,
+    SecretsFile,
+    ServerProperties,
+    Prompt,
+}
+
+impl PasswordSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            PasswordSource::CommandLine => "--password-rcon",
+            PasswordSource::Environment => "$SERVMGR_RCON_PASSWORD",
+            PasswordSource::SecretsFile => "rcon.password_file",
+            PasswordSource::ServerProperties => "server.properties",
+            PasswordSource::Prompt => "prompt",
+        }
+    }
+
+    /// Whether another servmgr process finds the password by itself (it doesn't when it was typed in)
+    pub fn is_findable(self) -> bool {
+        matches!(self, PasswordSource::Environment | PasswordSource::SecretsFile | PasswordSource::ServerProperties)
+    }
+}
+
+pub struct RconPassword {
+    pub password: String,
+    pub source: PasswordSource,
+}
+
+/// The first line of the secrets file, so the newline an editor or `echo` leaves at the end isn't part of the password
+pub fn read_secrets_file(path: &Path) -> io::Result<String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("Failed to read the RCON password file {} ({})", path.display(), err)))?;
+    match contents.lines().next().filter(|password| !password.is_empty()) {
+        Some(password) => Ok(String::from(password)),
+        None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("The RCON password file {} is empty", path.display()))),
+    }
+}
+
+/// The password from $SERVMGR_RCON_PASSWORD, the file rcon.password_file in servmgr.toml names or rcon.password in
+/// server.properties, `None` if none of them has one
+pub fn find_rcon_password(properties: &ServerProperties) -> io::Result<Option<RconPassword>> {
+    if let Ok(password) = env::var(RCON_PASSWORD_VARIABLE) && !password.is_empty() {
+        return Ok(Some(RconPassword { password, source: PasswordSource::Environment }));
+    }
+    if let Some(path) = &config::get().rcon_password_file {
+        return Ok(Some(RconPassword { password: read_secrets_file(path)?, source: PasswordSource::SecretsFile }));
+    }
+    Ok(properties.get("rcon.password")
+        .filter(|password| !password.is_empty())
+        .map(|password| RconPassword { password, source: PasswordSource::ServerProperties }))
+}
+
+/// Reads a line from the terminal with echo turned off
+fn prompt_password(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    // SAFETY: termios is a plain C struct of integers and arrays, all zeroes is a valid value and tcgetattr overwrites it
+    let mut terminal = unsafe { std::mem::zeroed::<libc::termios>() };
+    // SAFETY: the pointer is to a live termios we own, tcgetattr only writes through it
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut terminal) } != 0 { return Err(io::Error::last_os_error()); }
+    let echoing = terminal;
+    terminal.c_lflag &= !libc::ECHO;
+    terminal.c_lflag |= libc::ECHONL; // still moves to the next line on enter
+    // SAFETY: the pointer is to a live termios filled in by tcgetattr, tcsetattr only reads it
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &terminal) } != 0 { return Err(io::Error::last_os_error()); }
+
+    let mut password = String::new();
+    let result = io::stdin().lock().read_line(&mut password);
+    // SAFETY: as above, `echoing` is the unchanged settings tcgetattr returned
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &echoing) } != 0 {
+        // the password is read either way, but the shell is left not echoing what is typed
+        eprintln!("{}: Failed to turn the terminal's echo back on, run `stty echo` ({})", style("[WARN]").yellow(), io::Error::last_os_error());
+    }
+    result?;
+    Ok(String::from(password.trim_end_matches(['\r', '\n'])))
+}
+
+/// The RCON password for a command, `-p` if it was given, otherwise wherever `find_rcon_password` finds it, asking for
+/// it when none is set and servmgr runs in a terminal
+pub fn resolve_rcon_password(rcon_password: Option<&str>) -> Result<RconPassword, ServmgrError> {
+    if let Some(password) = rcon_password {
+        return Ok(RconPassword { password: String::from(password), source: PasswordSource::CommandLine });
+    }
+
+    let properties = ServerProperties::load_default()
+        .map_err(|err| ServmgrError::Config(format!("Failed to load the RCON settings ({})", err)))?;
+    let found = find_rcon_password(&properties).map_err(|err| ServmgrError::Config(err.to_string()))?;
+    if let Some(found) = found {
+        if found.source == PasswordSource::SecretsFile && let Some(path) = &config::get().rcon_password_file
+            && fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o077 != 0) {
+            eprintln!("{}: The RCON password file {} can be read by other users (chmod 600 it)", style("[WARN]").yellow(), path.display());
+        }
+        return Ok(found);
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(ServmgrError::Config(format!("No RCON password is set, put it in rcon.password in server.properties, ${} or the \
+            file rcon.password_file in servmgr.toml names", RCON_PASSWORD_VARIABLE)));
+    }
+    let password = prompt_password("RCON password: ").map_err(|err| ServmgrError::Failed(format!("Failed to read the RCON password ({})", err)))?;
+    Ok(RconPassword { password, source: PasswordSource::Prompt })
+}
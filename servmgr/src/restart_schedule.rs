@@ -0,0 +1,85 @@
+use std::io;
+use chrono::{DateTime, Local, TimeZone};
+use serde_json::{json, Value};
+use crate::config;
+use crate::rcon::{RconClient, RconConfig};
+
+/// How long before a scheduled restart (or a delayed stop) players are warned, in seconds
+pub const WARNING_SECONDS: [i64; 14] = [600, 300, 60, 30, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1];
+/// How far ahead `restart --in` can schedule a restart, restart.times covers anything regular
+pub const MAX_RESTART_DELAY: chrono::Duration = chrono::Duration::days(30);
+/// Days looked ahead for the next daily restart, a time that doesn't exist today (DST) exists tomorrow
+const DAILY_LOOKAHEAD_DAYS: u64 = 2;
+
+/// A restart the supervisor counts down to
+pub struct ScheduledRestart {
+    pub at: DateTime<Local>,
+    /// Whether the world is backed up right before the server stops
+    pub backup: bool,
+    /// Whether it is one of restart.times rather than one asked for with `restart --in`
+    pub daily: bool,
+    /// The last warning players got, in seconds before the restart
+    last_warning: Option<i64>,
+}
+
+impl ScheduledRestart {
+    pub fn new(at: DateTime<Local>, backup: bool, daily: bool) -> ScheduledRestart {
+        ScheduledRestart { at, backup, daily, last_warning: None }
+    }
+
+    /// Whether players were told about the restart yet (and so should be told when it is cancelled)
+    pub fn warned(&self) -> bool {
+        self.last_warning.is_some()
+    }
+
+    /// The seconds left to tell players about if a warning is due, the first warning comes as soon as the restart is
+    /// less than 10 minutes away (so a restart scheduled 2 minutes ahead is announced straight away)
+    pub fn due_warning(&mut self, now: DateTime<Local>) -> Option<i64> {
+        // rounded up so the 10 second warning isn't said as 9 seconds
+        let remaining = ((self.at - now).num_milliseconds() + 999) / 1000;
+        let warning = WARNING_SECONDS.iter().copied().filter(|seconds| *seconds >= remaining).min()?;
+        if remaining <= 0 || self.last_warning == Some(warning) { return None; }
+        self.last_warning = Some(warning);
+        Some(remaining)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({"at": self.at.to_rfc3339(), "backup": self.backup, "daily": self.daily})
+    }
+}
+
+/// The first of restart.times after `after`, `None` if no daily restarts are configured
+pub fn next_daily_restart(after: DateTime<Local>) -> Option<ScheduledRestart> {
+    let settings = &config::get().restart;
+    (0..=DAILY_LOOKAHEAD_DAYS)
+        .filter_map(|days| after.date_naive().checked_add_days(chrono::Days::new(days)))
+        .flat_map(|date| settings.times.iter().map(move |time| date.and_time(*time)))
+        .filter_map(|at| Local.from_local_datetime(&at).earliest())
+        .filter(|at| *at > after)
+        .min()
+        .map(|at| ScheduledRestart::new(at, settings.backup, true))
+}
+
+/// How long is left as players are told it, (e.g.) "5 minutes"
+pub fn describe_time_left(seconds: i64) -> String {
+    match seconds {
+        seconds if seconds >= 120 => format!("{} minutes", seconds / 60),
+        seconds if seconds >= 60 => String::from("1 minute"),
+        1 => String::from("1 second"),
+        seconds => format!("{} seconds", seconds),
+    }
+}
+
+/// Tells every player something in chat over `rcon`, the countdown's last seconds in red so they stand out, the same
+/// connection is kept for the whole countdown and only (re)connected when there is none yet or it stopped working
+pub fn broadcast(rcon: &mut Option<RconClient>, message: &str, urgent: bool) -> io::Result<()> {
+    let component = json!({"text": message, "color": if urgent { "red" } else { "gold" }});
+    let command = format!("tellraw @a {}", component);
+    if let Some(client) = rcon.as_mut() && client.command(&command).is_ok() { return Ok(()); }
+
+    *rcon = None;
+    let mut client = RconClient::connect(&RconConfig::load(None)?)?;
+    client.command(&command)?;
+    *rcon = Some(client);
+    Ok(())
+}
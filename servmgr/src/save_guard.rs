@@ -0,0 +1,153 @@
+use std::{fs, io::{self, Write}, ops::{Deref, DerefMut}, path::PathBuf, sync::Mutex, thread};
+use chrono::{DateTime, Local};
+use console::style;
+use serde_json::{json, Value};
+use signal_hook::{consts::{SIGINT, SIGTERM}, iterator::Signals};
+use crate::error::ServmgrError;
+use crate::rcon::{self, RconClient};
+use crate::{config, server_activator};
+
+/// Exists (and is locked) for as long as a servmgr process has the server's saving turned off, so a marker that
+/// nobody holds the lock on was left behind by a run that died before turning saving back on
+const SAVE_OFF_MARKER_FILE_NAME: &str = "servmgr_save_off.json";
+
+/// The RCON password of the guard that currently has saving turned off, for the signal handler to turn it back on with
+static SAVING_DISABLED_WITH: Mutex<Option<String>> = Mutex::new(None);
+
+fn save_off_marker_path() -> PathBuf {
+    config::get().server_directory.join(SAVE_OFF_MARKER_FILE_NAME)
+}
+
+fn send_save_on(rcon_password: Option<&str>) -> io::Result<()> {
+    rcon::open_responding_session(rcon_password)?.command("save-on").map(|_| ())
+}
+
+/// The window in which the server's autosave is turned off, saving is turned back on when the guard is restored or
+/// dropped, so every early return and panic in between leaves the server saving again
+pub struct SaveGuard {
+    rcon: Option<RconClient>,
+    rcon_password: String,
+    marker: Option<fs::File>,
+}
+
+impl SaveGuard {
+    /// Sends `save-off` through `rcon`, which the guard holds on to (and derefs to) until saving is turned back on
+    pub fn disable(rcon: RconClient, rcon_password: &str) -> Result<SaveGuard, ServmgrError> {
+        let marker_path = save_off_marker_path();
+        let mut marker = fs::OpenOptions::new().write(true).create(true).truncate(false).open(&marker_path)
+            .map_err(ServmgrError::filesystem("open", &marker_path))?;
+        match marker.try_lock() {
+            Ok(()) => {},
+            Err(fs::TryLockError::WouldBlock) => {
+                return Err(ServmgrError::Invalid(String::from("Another backup has the server's saving turned off, try again once it finishes")));
+            },
+            Err(fs::TryLockError::Error(err)) => return Err(ServmgrError::filesystem("lock", &marker_path)(err)),
+        }
+        marker.set_len(0)
+            .and_then(|_| write!(marker, "{}", json!({"pid": std::process::id(), "since": Local::now().to_rfc3339()})))
+            .map_err(ServmgrError::filesystem("write", &marker_path))?;
+        *SAVING_DISABLED_WITH.lock().unwrap() = Some(String::from(rcon_password));
+
+        // built before sending save-off so a save-off that fails halfway still gets a save-on
+        let mut guard = SaveGuard { rcon: Some(rcon), rcon_password: String::from(rcon_password), marker: Some(marker) };
+        guard.command("save-off").map_err(ServmgrError::rcon_command("save-off"))?;
+        Ok(guard)
+    }
+
+    /// Turns saving back on, handing back the RCON session for anything sent afterwards
+    pub fn restore(mut self) -> Result<RconClient, ServmgrError> {
+        self.turn_saving_on()?;
+        Ok(self.rcon.take().expect("the RCON session is only taken once saving is back on"))
+    }
+
+    /// Sends `save-on` on the guard's session, falling back to a new one if that session broke
+    fn turn_saving_on(&mut self) -> Result<(), ServmgrError> {
+        let sent = self.rcon.as_mut().map(|rcon| rcon.command("save-on"));
+        if !matches!(sent, Some(Ok(_))) {
+            let rcon = rcon::open_responding_session(Some(&self.rcon_password)).map_err(ServmgrError::rcon)?;
+            self.rcon = Some(rcon);
+            self.command("save-on").map_err(ServmgrError::rcon_command("save-on"))?;
+        }
+
+        *SAVING_DISABLED_WITH.lock().unwrap() = None;
+        if self.marker.take().is_some() { let _ = fs::remove_file(save_off_marker_path()); }
+        Ok(())
+    }
+}
+
+impl Deref for SaveGuard {
+    type Target = RconClient;
+
+    fn deref(&self) -> &RconClient {
+        self.rcon.as_ref().expect("the RCON session is held until saving is back on")
+    }
+}
+
+impl DerefMut for SaveGuard {
+    fn deref_mut(&mut self) -> &mut RconClient {
+        self.rcon.as_mut().expect("the RCON session is held until saving is back on")
+    }
+}
+
+impl Drop for SaveGuard {
+    fn drop(&mut self) {
+        if self.marker.is_none() { return; } // restored already
+
+        match self.turn_saving_on() {
+            Ok(()) => eprintln!("{}", style("=> Enabled auto-writing from RAM to DISK").dim()),
+            // the marker stays behind so the next servmgr run repairs it
+            Err(err) => eprintln!("{}: Failed to re-enable auto-writing from RAM to DISK: {}", style("[ERROR]").red(), err.summary()),
+        }
+    }
+}
+
+/// Turns saving back on before exiting when SIGINT or SIGTERM arrives, for commands that don't handle the signals
+/// themselves (the backup daemon lets an in-flight backup finish instead)
+pub fn restore_saving_on_signal() -> Result<(), ServmgrError> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])
+        .map_err(|err| ServmgrError::Failed(format!("Failed to install the signal handler ({})", err)))?;
+
+    thread::spawn(move || {
+        let Some(signal) = signals.forever().next() else { return; };
+        if let Some(rcon_password) = SAVING_DISABLED_WITH.lock().unwrap().take() {
+            eprintln!();
+            match send_save_on(Some(&rcon_password)) {
+                Ok(()) => {
+                    let _ = fs::remove_file(save_off_marker_path());
+                    eprintln!("{}: Interrupted, re-enabled auto-writing from RAM to DISK", style("[INFO]").cyan());
+                },
+                Err(err) => eprintln!("{}: Interrupted and failed to re-enable auto-writing from RAM to DISK ({})", style("[ERROR]").red(), err),
+            }
+        }
+        std::process::exit(128 + signal);
+    });
+    Ok(())
+}
+
+/// Turns saving back on if a servmgr run that turned it off died before turning it back on, using the RCON password
+/// from server.properties
+pub fn repair_stale_save_off() {
+    let marker_path = save_off_marker_path();
+    let Ok(marker) = fs::OpenOptions::new().read(true).write(true).open(&marker_path) else { return; };
+    if marker.try_lock().is_err() { return; } // a running backup holds it
+    if !server_activator::is_minecraft_server_running() {
+        let _ = fs::remove_file(&marker_path); // the server starts up saving again
+        return;
+    }
+
+    let since = fs::read_to_string(&marker_path).ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|marker| marker["since"].as_str().and_then(|since| DateTime::parse_from_rfc3339(since).ok()))
+        .map(|since| since.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| String::from("an unknown time"));
+
+    match send_save_on(None) {
+        Ok(()) => {
+            let _ = fs::remove_file(&marker_path);
+            eprintln!("{}: Re-enabled auto-writing from RAM to DISK, a backup run that died at {} left it disabled",
+                style("[INFO]").cyan(), since);
+        },
+        Err(err) => eprintln!("{}: A backup run that died at {} left auto-writing from RAM to DISK disabled and it couldn't be re-enabled ({})",
+            style("[ERROR]").red(), since, err),
+    }
+}
@@ -1,22 +1,364 @@
 use std::os::unix::process::CommandExt;
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::{fs, io::{self, BufRead}, path::Path, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread};
+use std::time::{Duration, Instant};
 use console::style;
+use serde_json::{json, Value};
+use crate::archive::ArchiveFormat;
+use crate::backup_catalog::BackupStorage;
+use crate::error::ServmgrError;
+use crate::rcon::{self, RconClient};
+use crate::server_log::LogWatcher;
+use crate::server_supervisor::{self, StopOutcome, LOG_FILE_NAME, PID_FILE_NAME};
+use crate::{backup_controller, backup_schedule, config, rcon_password, restart_schedule, save_guard};
 
-pub fn start_minecraft_server(detached: &bool) {
-    let mut start_cmd: Command = Command::new("bash");
-    start_cmd.arg("/home/mcadmin/minecraft_server/start_minecraft_server.sh");
-    if *detached { start_cmd.arg("-d"); }
+/// How long `start` waits for the supervisor to have the server running
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+/// Console lines shown when attaching, so there is some context for what comes next
+const ATTACH_BACKLOG_LINES: usize = 20;
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// What players are kicked with when `stop` isn't given a message
+const DEFAULT_STOP_MESSAGE: &str = "The server is shutting down";
+/// What the server logs once every world is written to disk on its way down
+const WORLD_SAVED_MESSAGE: &str = "All dimensions are saved";
 
-    println!("{}", style("Starting Minecraft Server...").cyan());
-    let err = start_cmd.exec();
-    eprintln!("{}: {}", style("Failed to start minecraft server").red(), err);
+/// Checks whether a java process running the server jar exists, regardless of how it was started
+pub fn is_minecraft_server_running() -> bool {
+    let Ok(processes) = fs::read_dir("/proc") else { return false; };
+    let jvm = &config::get().jvm;
+    let jar_name = Path::new(&jvm.jar).file_name().unwrap_or_default().as_encoded_bytes();
+    let java_name = Path::new(&jvm.java).file_name().unwrap_or_default().as_encoded_bytes();
+
+    processes.filter_map(Result::ok)
+        .filter(|process| process.file_name().to_string_lossy().chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|process| fs::read(process.path().join("cmdline")).ok())
+        .any(|cmdline| {
+            let args: Vec<&[u8]> = cmdline.split(|byte| *byte == 0).collect();
+            args.first().is_some_and(|program| program.ends_with(java_name))
+                && args.iter().any(|arg| arg.ends_with(jar_name))
+        })
+}
+
+/// A server that is up but wasn't started by servmgr can't be stopped or restarted by it
+fn unsupervised_server_error() -> ServmgrError {
+    ServmgrError::Invalid(String::from("The Minecraft server is running but wasn't started by servmgr, stop it from its console first"))
+}
+
+/// Starts the server supervisor in the background and waits for it to have the server running
+fn spawn_supervisor() -> Result<i32, ServmgrError> {
+    let log_path = server_supervisor::supervisor_file_path(LOG_FILE_NAME);
+    let spawn_result = std::env::current_exe()
+        .and_then(|executable| {
+            let log_file = fs::OpenOptions::new().create(true).append(true).open(&log_path)?;
+            let mut command = Command::new(executable);
+            // the supervisor reads the same config file, settings from the environment are inherited anyway
+            if config::get().path.exists() { command.env("SERVMGR_CONFIG", &config::get().path); }
+            command
+                .arg("supervise")
+                .stdin(Stdio::null())
+                .stdout(log_file.try_clone()?)
+                .stderr(log_file)
+                .process_group(0) // keeps the server running when the terminal that started it closes
+                .spawn()
+        });
+    let mut supervisor = spawn_result.map_err(ServmgrError::process("the server supervisor"))?;
+
+    let started_at = Instant::now();
+    loop {
+        if let Ok(Some(pid)) = server_supervisor::running_server_pid() { return Ok(pid); }
+        if let Ok(Some(status)) = supervisor.try_wait() {
+            return Err(ServmgrError::Failed(format!("The server supervisor exited with {} before the server started, see {}", status, log_path.display())));
+        }
+        if started_at.elapsed() >= START_TIMEOUT {
+            return Err(ServmgrError::Failed(format!("The server didn't start within {} seconds, see {}", START_TIMEOUT.as_secs(), log_path.display())));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Starts the minecraft server under the supervisor, then attaches to its console unless `detached`
+pub fn start_minecraft_server(detached: bool) -> Result<(), ServmgrError> {
+    let pid_path = server_supervisor::supervisor_file_path(PID_FILE_NAME);
+    match server_supervisor::running_server_pid().map_err(ServmgrError::filesystem("read", &pid_path))? {
+        Some(pid) => println!("{}: The Minecraft server is already running (pid {})", style("[INFO]").cyan(), pid),
+        None if server_supervisor::is_supervisor_running() => println!("{}: The Minecraft server is restarting after a crash, \
+            `servmgr restart` starts it right away", style("[INFO]").cyan()),
+        None if is_minecraft_server_running() => return Err(unsupervised_server_error()),
+        None => {
+            println!("{}", style("Starting Minecraft Server...").cyan());
+            let pid = spawn_supervisor()?;
+            println!("{}: Started the Minecraft server (pid {}), logging to {}",
+                style("[INFO]").cyan(), pid, server_supervisor::supervisor_file_path(LOG_FILE_NAME).display());
+        },
+    }
+
+    if detached { Ok(()) } else { attach_console() }
+}
+
+/// Follows the server's console output and sends every line typed to it, until stdin closes (Ctrl-D) or the server stops
+pub fn attach_console() -> Result<(), ServmgrError> {
+    if !server_supervisor::is_supervisor_running() {
+        return Err(ServmgrError::Invalid(String::from("The Minecraft server isn't running under servmgr, start it with `servmgr start`")));
+    }
+
+    let log_path = server_supervisor::supervisor_file_path(LOG_FILE_NAME);
+    let mut watcher = LogWatcher::open(&log_path);
+    let backlog = fs::read_to_string(&log_path).map_err(ServmgrError::filesystem("read", &log_path))?;
+    let backlog: Vec<&str> = backlog.lines().collect();
+    for line in &backlog[backlog.len().saturating_sub(ATTACH_BACKLOG_LINES)..] { println!("{}", line); }
+    println!("{}", style("=> Attached to the server console, Ctrl-D detaches (the server keeps running)").dim());
+
+    let (input_sender, input) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break; };
+            if input_sender.send(line).is_err() { break; }
+        }
+    });
+
+    loop {
+        for line in watcher.read_new_lines().map_err(ServmgrError::filesystem("read", &log_path))? { println!("{}", line); }
+        match input.try_recv() {
+            Ok(line) => if let Err(err) = server_supervisor::send_console_input(&line) {
+                eprintln!("{}: Failed to send '{}' to the server console ({})", style("[ERROR]").red(), line, err);
+            },
+            Err(mpsc::TryRecvError::Disconnected) => break,
+            Err(mpsc::TryRecvError::Empty) => {},
+        }
+        if !server_supervisor::is_supervisor_running() {
+            for line in watcher.read_new_lines().unwrap_or_default() { println!("{}", line); }
+            println!("{}", style("[INFO] The Minecraft server stopped").cyan());
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    println!("{}", style("=> Detached from the server console").dim());
+    Ok(())
+}
+
+/// Turns the supervisor's answer to a stop or restart into how long the server took to go down, or the error to report
+/// when it had to be terminated (and so may not have saved)
+fn check_stop_response(response: &Value, timeout: Duration) -> Result<f64, ServmgrError> {
+    let seconds = response["seconds"].as_f64().unwrap_or_default();
+    match response["outcome"].as_str().and_then(StopOutcome::from_name) {
+        Some(StopOutcome::Exited) => Ok(seconds),
+        Some(outcome) => Err(ServmgrError::Failed(format!("The Minecraft server didn't stop within {} seconds and was {}, \
+            the world may not have been saved", timeout.as_secs(), outcome.name()))),
+        None => Err(ServmgrError::Failed(String::from("The server supervisor gave a malformed answer"))),
+    }
 }
 
-pub fn stop_minecraft_server() {
-    let mut start_cmd: Command = Command::new("bash");
-    start_cmd.arg("/home/mcadmin/minecraft_server/stop_minecraft_server.sh");
+/// How `stop` goes about it, the defaults stop the server straight away without a word to players
+#[derive(Default)]
+pub struct StopOptions {
+    /// How long the server gets to exit before it is terminated, servmgr.toml's server.stop_timeout if `None`
+    pub timeout: Option<chrono::Duration>,
+    /// How long players are counted down before the stop
+    pub delay: Option<chrono::Duration>,
+    /// What players are kicked with, vanilla's "Server closed" if `None` (and there is no countdown)
+    pub message: Option<String>,
+    pub backup_first: bool,
+    pub rcon_password: Option<String>,
+}
+
+impl StopOptions {
+    /// Whether players are told about the stop, which needs RCON
+    fn warns_players(&self) -> bool {
+        self.delay.is_some() || self.message.is_some() || self.backup_first
+    }
+}
+
+/// Sends a countdown command, the countdown carries on when one doesn't get through and only the first failure is reported
+fn send_countdown_command(rcon: &mut RconClient, command: &str, failed: &mut bool) {
+    if let Err(err) = rcon.command(command) && !*failed {
+        eprintln!("{}: Failed to warn players of the stop ({})", style("[WARN]").yellow(), err);
+        *failed = true;
+    }
+}
+
+/// Counts players down to the stop in the action bar every second, with a title and a chat message at the usual
+/// warning times, fails if servmgr is interrupted (which cancels the stop) on the way
+fn count_down_to_stop(rcon: &mut RconClient, delay: Duration, message: &str) -> Result<(), ServmgrError> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let signal_id = signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&interrupted))
+        .map_err(|err| ServmgrError::Failed(format!("Failed to install the signal handler ({})", err)))?;
+    let status_spinner = backup_controller::new_status_spinner("Counting down to the stop, Ctrl-C cancels it...");
+
+    let stop_at = Instant::now() + delay;
+    let mut failed = false;
+    let mut announced = false;
+    let mut last_second = None;
+    let result = loop {
+        if interrupted.load(Ordering::Relaxed) {
+            send_countdown_command(rcon, "title @a clear", &mut failed);
+            send_countdown_command(rcon, &format!("tellraw @a {}", json!({"text": "The server is no longer stopping", "color": "green"})), &mut failed);
+            break Err(ServmgrError::Failed(String::from("Interrupted, the Minecraft server keeps running")));
+        }
+        let remaining = stop_at.saturating_duration_since(Instant::now());
+        let seconds = remaining.as_millis().div_ceil(1000) as i64;
+        if seconds == 0 { break Ok(()); }
+
+        if last_second != Some(seconds) {
+            last_second = Some(seconds);
+            let time_left = restart_schedule::describe_time_left(seconds);
+            let color = if seconds <= 10 { "red" } else { "gold" };
+            send_countdown_command(rcon, &format!("title @a actionbar {}",
+                json!({"text": format!("Server stopping in {}:{:02}", seconds / 60, seconds % 60), "color": color})), &mut failed);
+            if !announced || restart_schedule::WARNING_SECONDS.contains(&seconds) {
+                // the last seconds tick down in the title itself, chat only gets the warnings up to them
+                let (title, subtitle) = match seconds {
+                    seconds if seconds < 10 => (seconds.to_string(), String::from("Server stopping")),
+                    _ => (String::from("Server stopping"), format!("in {}", time_left)),
+                };
+                send_countdown_command(rcon, &format!("title @a subtitle {}", json!({"text": subtitle, "color": color})), &mut failed);
+                send_countdown_command(rcon, &format!("title @a title {}", json!({"text": title, "color": color})), &mut failed);
+                if !announced || seconds >= 10 {
+                    send_countdown_command(rcon, &format!("tellraw @a {}",
+                        json!({"text": format!("The server stops in {}: {}", time_left, message), "color": color})), &mut failed);
+                }
+                announced = true;
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    status_spinner.finish_and_clear();
+    signal_hook::low_level::unregister(signal_id);
+    result
+}
+
+/// Kicks every online player with `message`, returning how many there were
+fn kick_players(rcon: &mut RconClient, message: &str) -> Result<usize, ServmgrError> {
+    let players = rcon::parse_player_names(&rcon.command("list").map_err(ServmgrError::rcon_command("list"))?);
+    for player in &players {
+        let command = format!("kick {} {}", player, message);
+        rcon.command(&command).map_err(ServmgrError::rcon_command(&command))?;
+    }
+    Ok(players.len())
+}
+
+/// Has the supervisor send `stop` and returns once the server has exited and saved the world, the server is
+/// terminated if it takes longer than the timeout, players are counted down to the stop and kicked with a message first
+/// when the options ask for it (along with a backup)
+pub fn stop_minecraft_server(options: &StopOptions) -> Result<(), ServmgrError> {
+    let pid_path = server_supervisor::supervisor_file_path(PID_FILE_NAME);
+    let timeout = options.timeout.unwrap_or(config::get().stop_timeout).to_std().unwrap_or_default();
+    if server_supervisor::running_server_pid().map_err(ServmgrError::filesystem("read", &pid_path))?.is_none() {
+        if server_supervisor::is_supervisor_running() {
+            // there is no server to warn or save, stopping calls off the restart after the crash
+            check_stop_response(&server_supervisor::request_stop(timeout).map_err(ServmgrError::process("the server supervisor"))?, timeout)?;
+            while server_supervisor::is_supervisor_running() { thread::sleep(POLL_INTERVAL); }
+            println!("{}: The Minecraft server was restarting after a crash, it is left stopped", style("[INFO]").cyan());
+            return Ok(());
+        }
+        if is_minecraft_server_running() { return Err(unsupervised_server_error()); }
+        println!("{}: The Minecraft server is not running", style("[INFO]").cyan());
+        return Ok(());
+    }
+    let started_at = Instant::now();
 
     println!("{}", style("Stopping Minecraft Server...").cyan());
-    let err = start_cmd.exec();
-    eprintln!("{}: {}", style("Failed to stop minecraft server").red(), err);
-}
\ No newline at end of file
+    if options.warns_players() {
+        let rcon_password = rcon_password::resolve_rcon_password(options.rcon_password.as_deref())?;
+        let message = options.message.as_deref().unwrap_or(DEFAULT_STOP_MESSAGE);
+        let mut rcon = rcon::open_responding_session(Some(&rcon_password.password)).map_err(ServmgrError::rcon)?;
+        if let Some(delay) = options.delay {
+            count_down_to_stop(&mut rcon, delay.to_std().unwrap_or_default(), message)?;
+            println!("{} {}", style("●").green(), style(format!("Counted players down for {}", backup_schedule::format_duration(delay))).cyan());
+        }
+        if options.backup_first {
+            let backup_options = backup_controller::BackupOptions {
+                storage: BackupStorage::Archive(ArchiveFormat::TarGz), level: None, schedule: None,
+                save_timeout: backup_controller::DEFAULT_SAVE_TIMEOUT, notifications: None,
+            };
+            save_guard::restore_saving_on_signal()?;
+            if let Err(err) = backup_controller::backup_minecraft_server(&rcon_password.password, &backup_options) {
+                let _ = rcon::open_responding_session(Some(&rcon_password.password)).and_then(|mut rcon| rcon.command(&format!("tellraw @a {}",
+                    json!({"text": "The server is no longer stopping", "color": "green"}))));
+                return Err(ServmgrError::Failed(format!("The backup failed so the Minecraft server was left running ({})", err.summary())));
+            }
+            rcon = rcon::open_responding_session(Some(&rcon_password.password)).map_err(ServmgrError::rcon)?;
+        }
+        let kicked = kick_players(&mut rcon, message)?;
+        println!("{} {}", style("●").green(), style(format!("Kicked {} player{} ({})", kicked, if kicked == 1 { "" } else { "s" }, message)).cyan());
+    }
+
+    let log_path = server_supervisor::supervisor_file_path(LOG_FILE_NAME);
+    let mut console = LogWatcher::open(&log_path);
+    let status_spinner = backup_controller::new_status_spinner("Waiting for the server to save and exit...");
+    let response = server_supervisor::request_stop(timeout);
+    status_spinner.finish_and_clear();
+    let seconds = check_stop_response(&response.map_err(ServmgrError::process("the server supervisor"))?, timeout)?;
+
+    while server_supervisor::is_supervisor_running() { thread::sleep(POLL_INTERVAL); }
+    let saved = console.read_new_lines().unwrap_or_default().iter().any(|line| line.contains(WORLD_SAVED_MESSAGE));
+    if saved {
+        println!("{} {}", style("●").green(), style("The server saved the world").cyan());
+    } else {
+        eprintln!("{}: The server exited without logging that it saved the world, check {}", style("[WARN]").yellow(), log_path.display());
+    }
+    println!("{}: Stopped the Minecraft server in {:.1}s (it took {:.1}s to exit)",
+        style("[INFO]").cyan(), started_at.elapsed().as_secs_f64(), seconds);
+    Ok(())
+}
+
+/// Stops the server and starts it again under the same supervisor (right away if it is waiting to be restarted after a
+/// crash), or just starts it if it isn't running
+pub fn restart_minecraft_server(timeout: Option<chrono::Duration>) -> Result<(), ServmgrError> {
+    if !server_supervisor::is_supervisor_running() {
+        return start_minecraft_server(true);
+    }
+    let timeout = timeout.unwrap_or(config::get().stop_timeout).to_std().unwrap_or_default();
+
+    println!("{}", style("Restarting Minecraft Server...").cyan());
+    let status_spinner = backup_controller::new_status_spinner("Waiting for the server to save and exit...");
+    let response = server_supervisor::request_restart(timeout);
+    status_spinner.finish_and_clear();
+    let response = response.map_err(ServmgrError::process("the server supervisor"))?;
+    let seconds = check_stop_response(&response, timeout)?;
+
+    println!("{}: Restarted the Minecraft server (pid {}), it stopped in {:.1}s", style("[INFO]").cyan(), response["server_pid"], seconds);
+    Ok(())
+}
+
+fn describe_scheduled_restart(restart: &Value) -> String {
+    let at = restart["at"].as_str().and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok()).map(|at| at.with_timezone(&chrono::Local));
+    match at {
+        Some(at) => format!("{} (in {}{}{})", at.format("%Y-%m-%d %H:%M:%S"), backup_schedule::format_duration(at - chrono::Local::now()),
+            if restart["daily"] == true { ", daily" } else { "" }, if restart["backup"] == true { ", backing up first" } else { "" }),
+        None => String::from("-"),
+    }
+}
+
+/// Has the supervisor restart the server after `delay`, warning players in chat as it comes closer (10 minutes, 5
+/// minutes, 1 minute, 30 seconds and the last 10 seconds) and backing up the world right before the stop if `backup`
+pub fn schedule_restart(delay: chrono::Duration, backup: bool) -> Result<(), ServmgrError> {
+    if !server_supervisor::is_supervisor_running() {
+        if is_minecraft_server_running() { return Err(unsupervised_server_error()); }
+        return Err(ServmgrError::Invalid(String::from("The Minecraft server isn't running under servmgr, start it with `servmgr start`")));
+    }
+    if delay > restart_schedule::MAX_RESTART_DELAY {
+        return Err(ServmgrError::Invalid(format!("--in can't be more than {}, set restart.times in servmgr.toml for regular restarts",
+            backup_schedule::format_duration(restart_schedule::MAX_RESTART_DELAY))));
+    }
+    let response = server_supervisor::request_scheduled_restart(delay.to_std().unwrap_or_default(), backup)
+        .map_err(ServmgrError::process("the server supervisor"))?;
+    println!("{}: The Minecraft server restarts at {}, cancel with `servmgr restart --cancel`",
+        style("[INFO]").cyan(), describe_scheduled_restart(&response));
+    Ok(())
+}
+
+/// Cancels the scheduled restart, telling players if they were warned of it, the next of restart.times (if any) is
+/// scheduled in its place
+pub fn cancel_scheduled_restart() -> Result<(), ServmgrError> {
+    if !server_supervisor::is_supervisor_running() {
+        return Err(ServmgrError::Invalid(String::from("The Minecraft server isn't running under servmgr, no restart is scheduled")));
+    }
+    let response = server_supervisor::request_restart_cancellation().map_err(|err| ServmgrError::Invalid(err.to_string()))?;
+    println!("{}: Cancelled the restart at {}", style("[INFO]").cyan(), describe_scheduled_restart(&response["cancelled"]));
+    if !response["next"].is_null() {
+        println!("{}: The next daily restart is at {}", style("[INFO]").cyan(), describe_scheduled_restart(&response["next"]));
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::{fs, io::{self, Read, Seek, SeekFrom}, path::{Path, PathBuf}, thread, time::{Duration, Instant}};
+use crate::config;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub fn latest_log_path() -> PathBuf {
+    config::get().server_directory.join("logs").join("latest.log")
+}
+
+/// Follows the server log from where it ended when the watcher was opened, picking up from the start again when the
+/// server rotates the log on a restart
+pub struct LogWatcher {
+    path: PathBuf,
+    offset: u64,
+    partial_line: Vec<u8>,
+}
+
+impl LogWatcher {
+    pub fn open(path: &Path) -> LogWatcher {
+        let offset = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default();
+        LogWatcher { path: path.to_path_buf(), offset, partial_line: Vec::new() }
+    }
+
+    /// Every complete line written since the last read
+    pub fn read_new_lines(&mut self) -> io::Result<Vec<String>> {
+        let mut log = match fs::File::open(&self.path) {
+            Ok(log) => log,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        if log.metadata()?.len() < self.offset { // rotated
+            self.offset = 0;
+            self.partial_line.clear();
+        }
+
+        log.seek(SeekFrom::Start(self.offset))?;
+        let mut written = Vec::new();
+        self.offset += log.read_to_end(&mut written)? as u64;
+        self.partial_line.extend_from_slice(&written);
+
+        let Some(last_newline) = self.partial_line.iter().rposition(|byte| *byte == b'\n') else { return Ok(Vec::new()); };
+        let complete: Vec<u8> = self.partial_line.drain(..=last_newline).collect();
+        Ok(String::from_utf8_lossy(&complete).lines().map(String::from).collect())
+    }
+
+    /// Waits for a line that `matches`, `None` if none was written within `timeout`
+    pub fn wait_for_line(&mut self, matches: impl Fn(&str) -> bool, timeout: Duration) -> io::Result<Option<String>> {
+        let started_at = Instant::now();
+        loop {
+            if let Some(line) = self.read_new_lines()?.into_iter().find(|line| matches(line)) { return Ok(Some(line)); }
+            if started_at.elapsed() >= timeout { return Ok(None); }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::{io::{self, Read, Write}, net::{TcpStream, ToSocketAddrs}, time::{Duration, Instant}};
+use serde_json::Value;
+use crate::server_properties::ServerProperties;
+
+// the Server List Ping exchange (https://minecraft.wiki/w/Java_Edition_protocol/Server_List_Ping)
+const HANDSHAKE_PACKET_ID: i32 = 0x00;
+const STATUS_REQUEST_PACKET_ID: i32 = 0x00;
+const PING_PACKET_ID: i32 = 0x01;
+const STATUS_NEXT_STATE: i32 = 1;
+// servers answer a status request whatever protocol the handshake names, -1 is what clients send when they don't know
+const HANDSHAKE_PROTOCOL_VERSION: i32 = -1;
+
+const DEFAULT_SERVER_PORT: u16 = 25565;
+const MAX_PACKET_LENGTH: i32 = 2 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the server tells clients in the multiplayer server list
+pub struct ServerListPing {
+    pub version: String,
+    pub protocol: i64,
+    /// The MOTD, a text component or a plain string with legacy `§` formatting codes
+    pub description: Value,
+    pub online_players: u64,
+    pub max_players: u64,
+    /// Some of the online players, the server decides how many (vanilla sends up to 12)
+    pub player_sample: Vec<String>,
+    pub latency: Duration,
+}
+
+/// The address players connect to, `server-ip` and `server-port` from server.properties
+pub fn server_address(properties: &ServerProperties) -> io::Result<(String, u16)> {
+    let host = properties.get("server-ip")
+        .filter(|ip| !ip.trim().is_empty())
+        .unwrap_or_else(|| String::from("127.0.0.1"));
+    let port = match properties.get("server-port") {
+        Some(port) => port.trim().parse::<u16>()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid server-port '{}' in server.properties", port)))?,
+        None => DEFAULT_SERVER_PORT,
+    };
+    Ok((host, port))
+}
+
+fn write_varint(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<i32> {
+    let mut value = 0u32;
+    for position in 0..5 {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7F) as u32) << (7 * position);
+        if byte[0] & 0x80 == 0 { return Ok(value as i32); }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "VarInt is too long"))
+}
+
+fn write_string(buffer: &mut Vec<u8>, string: &str) {
+    write_varint(buffer, string.len() as i32);
+    buffer.extend_from_slice(string.as_bytes());
+}
+
+fn write_packet(stream: &mut TcpStream, packet_id: i32, payload: &[u8]) -> io::Result<()> {
+    let mut body = Vec::new();
+    write_varint(&mut body, packet_id);
+    body.extend_from_slice(payload);
+
+    let mut packet = Vec::new();
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(&body);
+    stream.write_all(&packet)
+}
+
+/// Reads a packet, returning its id and what follows it
+fn read_packet(stream: &mut TcpStream) -> io::Result<(i32, Vec<u8>)> {
+    let length = read_varint(stream)?;
+    if !(1..=MAX_PACKET_LENGTH).contains(&length) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid packet length {}", length)));
+    }
+    let mut body = vec![0u8; length as usize];
+    stream.read_exact(&mut body)?;
+
+    let mut body = io::Cursor::new(body);
+    let packet_id = read_varint(&mut body)?;
+    let position = body.position() as usize;
+    Ok((packet_id, body.into_inner().split_off(position)))
+}
+
+/// Asks the server for its status like the multiplayer server list does, then times a ping round trip
+pub fn ping(host: &str, port: u16) -> io::Result<ServerListPing> {
+    let address = (host, port).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Could not resolve server host '{}'", host)))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.set_nodelay(true)?;
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, HANDSHAKE_PROTOCOL_VERSION);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, STATUS_NEXT_STATE);
+    write_packet(&mut stream, HANDSHAKE_PACKET_ID, &handshake)?;
+    write_packet(&mut stream, STATUS_REQUEST_PACKET_ID, &[])?;
+
+    let (packet_id, payload) = read_packet(&mut stream)?;
+    if packet_id != STATUS_REQUEST_PACKET_ID {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Expected a status response, got packet {:#04x}", packet_id)));
+    }
+    let mut payload = io::Cursor::new(payload);
+    let json_length = read_varint(&mut payload)? as usize;
+    let mut status = vec![0u8; json_length];
+    payload.read_exact(&mut status)?;
+    let status: Value = serde_json::from_slice(&status).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let sent_at = Instant::now();
+    let payload = chrono::Local::now().timestamp_millis();
+    write_packet(&mut stream, PING_PACKET_ID, &payload.to_be_bytes())?;
+    let (packet_id, echoed) = read_packet(&mut stream)?;
+    if packet_id != PING_PACKET_ID || echoed != payload.to_be_bytes() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "The server answered the ping with something other than a pong"));
+    }
+    let latency = sent_at.elapsed();
+
+    Ok(ServerListPing {
+        version: status["version"]["name"].as_str().unwrap_or_default().to_string(),
+        protocol: status["version"]["protocol"].as_i64().unwrap_or_default(),
+        description: status["description"].clone(),
+        online_players: status["players"]["online"].as_u64().unwrap_or_default(),
+        max_players: status["players"]["max"].as_u64().unwrap_or_default(),
+        player_sample: status["players"]["sample"].as_array().into_iter().flatten()
+            .filter_map(|player| player["name"].as_str().map(String::from))
+            .collect(),
+        latency,
+    })
+}
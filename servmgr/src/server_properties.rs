@@ -0,0 +1,85 @@
+use std::{fs, io, path::{Path, PathBuf}};
+use crate::config;
+
+pub fn server_properties_path() -> PathBuf {
+    config::get().server_directory.join("server.properties")
+}
+
+/// An editable view of `server.properties` that keeps comments, ordering and unknown keys intact when saved
+pub struct ServerProperties {
+    path: PathBuf,
+    lines: Vec<String>,
+}
+
+impl ServerProperties {
+    pub fn load(path: &Path) -> io::Result<ServerProperties> {
+        let contents = fs::read_to_string(path)?;
+
+        Ok(ServerProperties {
+            path: path.to_path_buf(),
+            lines: contents.lines().map(String::from).collect(),
+        })
+    }
+
+    pub fn load_default() -> io::Result<ServerProperties> {
+        ServerProperties::load(&server_properties_path())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.lines.iter()
+            .filter_map(|line| split_property_line(line))
+            .find(|(line_key, _)| *line_key == key)
+            .map(|(_, value)| unescape_value(value))
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        let new_line = format!("{}={}", key, escape_value(value));
+
+        match self.lines.iter().position(|line| split_property_line(line).is_some_and(|(line_key, _)| line_key == key)) {
+            Some(index) => self.lines[index] = new_line,
+            None => self.lines.push(new_line),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+
+        // write to a temporary file first so a failed write never leaves a truncated server.properties behind
+        let temporary_path = self.path.with_extension("properties.tmp");
+        fs::write(&temporary_path, contents)?;
+        fs::rename(&temporary_path, &self.path)
+    }
+}
+
+fn split_property_line(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+        return None;
+    }
+
+    let (key, value) = trimmed.split_once('=')?;
+    Some((key.trim(), value))
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() { unescaped.push(escaped); }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ':' | '=') { escaped.push('\\'); }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,253 @@
+use std::{io, time::{Duration, Instant}};
+use chrono::{DateTime, Local};
+use console::style;
+use serde_json::{json, Value};
+use crate::error::ServmgrError;
+use crate::rcon::{RconClient, RconConfig};
+use crate::server_ping::{self, ServerListPing};
+use crate::server_properties::ServerProperties;
+use crate::{backup_catalog, backup_daemon, backup_schedule, backup_state, crash_history, disk_usage, rcon, server_activator, server_supervisor, text_component, world_manager};
+
+/// What the RCON interface answered and how quickly
+struct RconHealth {
+    latency: Duration,
+    players: Vec<String>,
+    /// Ticks per second and milliseconds per tick (if the server reports them), `None` if the server has no command
+    /// that reports its tick rate
+    tick_rate: Option<(f64, Option<f64>)>,
+}
+
+/// The number that follows `label` in a command's response (e.g.) 1.2 after "Average time per tick: " in "... 1.2ms"
+fn number_after(response: &str, label: &str) -> Option<f64> {
+    let (_, rest) = response.split_once(label)?;
+    let rest = rest.trim_start_matches('*'); // paper marks a tps above 20 with a *
+    let end = rest.find(|character: char| !character.is_ascii_digit() && character != '.').unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// TPS and MSPT from vanilla's `tick query` (1.20.3+), falling back to paper's `tps` which only has the TPS
+fn query_tick_rate(rcon: &mut RconClient) -> Option<(f64, Option<f64>)> {
+    if let Ok(response) = rcon.command("tick query")
+        && let Some(milliseconds_per_tick) = number_after(&response, "Average time per tick: ") {
+        let target_tick_rate = number_after(&response, "Target tick rate: ").unwrap_or(20.0);
+        // a server that keeps up runs at its target rate however little time a tick takes
+        let ticks_per_second = if milliseconds_per_tick > 0.0 { target_tick_rate.min(1000.0 / milliseconds_per_tick) } else { target_tick_rate };
+        return Some((ticks_per_second, Some(milliseconds_per_tick)));
+    }
+
+    let response = rcon.command("tps").ok()?;
+    let ticks_per_second = number_after(&console::strip_ansi_codes(&text_component::render_response(&response)), "15m: ")?;
+    Some((ticks_per_second, None))
+}
+
+fn check_rcon() -> io::Result<RconHealth> {
+    let mut rcon = RconClient::connect(&RconConfig::load(None)?)?;
+    let sent_at = Instant::now();
+    let list_response = rcon.command("list")?;
+    let latency = sent_at.elapsed();
+    Ok(RconHealth { latency, players: rcon::parse_player_names(&list_response), tick_rate: query_tick_rate(&mut rcon) })
+}
+
+fn check_server_list_ping() -> io::Result<ServerListPing> {
+    let (host, port) = server_ping::server_address(&ServerProperties::load_default()?)?;
+    server_ping::ping(&host, port)
+}
+
+/// The MOTD as the server list shows it, with its colors for the terminal
+fn render_motd(description: &Value) -> String {
+    match description {
+        Value::String(text) => text_component::render_response(text),
+        component => text_component::render_response(&component.to_string()),
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 10_000.0).round() / 10.0
+}
+
+/// Checks the server process, its RCON interface, the server list ping and the backups, then prints what they found
+/// along with how much disk the world and backups take up, fails when any check did (with exit code 1, which is what
+/// Docker's HEALTHCHECK takes as unhealthy), backups only count when `max_backup_age` is given, `quick` leaves out RCON
+/// (each session is logged by the server) and the disk usage (which walks every backup) for checks that run every minute
+pub fn show_server_status(max_backup_age: Option<chrono::Duration>, quick: bool, json_output: bool) -> Result<(), ServmgrError> {
+    let socket_path = server_supervisor::supervisor_file_path(server_supervisor::SOCKET_FILE_NAME);
+    let supervisor = server_supervisor::read_supervisor_status()
+        .map_err(ServmgrError::filesystem("read the server's status from", &socket_path))?;
+    let running = match &supervisor {
+        Some(supervisor) => supervisor["server_pid"].is_number(),
+        None => server_activator::is_minecraft_server_running(),
+    };
+    let server_started_at = supervisor.as_ref()
+        .and_then(|supervisor| supervisor["server_started_at"].as_str())
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at.with_timezone(&Local));
+    let uptime = server_started_at.map(|at| Local::now() - at);
+    let last_crash = crash_history::read_crash_history().ok().and_then(|mut history| history.pop());
+    let last_crash_at = last_crash.as_ref()
+        .and_then(|crash| crash["at"].as_str())
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at.with_timezone(&Local));
+
+    let rcon_health = (!quick).then(check_rcon);
+    let server_list_ping = check_server_list_ping();
+
+    let world_path = world_manager::active_world_path();
+    let backups_path = backup_catalog::backups_directory();
+    let disk_sizes = (!quick).then(|| (disk_usage::directory_size(&world_path), disk_usage::directory_size(&backups_path)));
+
+    let backup_state = backup_state::read_backup_state().unwrap_or_else(|_| json!({}));
+    let daemon_pid = backup_daemon::running_daemon_pid().ok().flatten();
+    let last_backup_at = backup_state["last_success"]["at"].as_str()
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at.with_timezone(&Local));
+    let last_backup_age = last_backup_at.map(|at| Local::now() - at);
+    let backups_fresh = last_backup_age.is_some_and(|age| max_backup_age.is_none_or(|max_age| age <= max_age));
+    let next_backup_at = daemon_pid.and(backup_state["next_run"]["at"].as_str())
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at.with_timezone(&Local));
+
+    let mut problems = Vec::new();
+    if !running { problems.push(String::from("the server isn't running")); }
+    if let Some(Err(err)) = &rcon_health { problems.push(format!("RCON isn't responding ({})", err)); }
+    if let Err(err) = &server_list_ping { problems.push(format!("the server list ping failed ({})", err)); }
+    if let Some(max_age) = max_backup_age && !backups_fresh {
+        problems.push(match last_backup_age {
+            Some(age) => format!("the last successful backup is {} old (max {})", backup_schedule::format_duration(age), backup_schedule::format_duration(max_age)),
+            None => String::from("there has never been a successful backup"),
+        });
+    }
+    let unhealthy = || ServmgrError::Failed(format!("Unhealthy: {}", problems.join(", ")));
+
+    if json_output {
+        let status = json!({
+            "healthy": problems.is_empty(),
+            "problems": problems,
+            "server": {
+                "running": running,
+                "supervised": supervisor.is_some(),
+                "pid": supervisor.as_ref().map(|supervisor| supervisor["server_pid"].clone()),
+                "supervisor_pid": supervisor.as_ref().map(|supervisor| supervisor["pid"].clone()),
+                "state": supervisor.as_ref().map(|supervisor| supervisor["state"].clone()),
+                "started_at": server_started_at.map(|at| at.to_rfc3339()),
+                "uptime_seconds": uptime.map(|uptime| uptime.num_seconds()),
+                "restarts": supervisor.as_ref().map(|supervisor| supervisor["restarts"].clone()),
+                "crashes": supervisor.as_ref().map(|supervisor| supervisor["crashes"].clone()),
+                "restart_at": supervisor.as_ref().map(|supervisor| supervisor["restart_at"].clone()),
+                "scheduled_restart": supervisor.as_ref().map(|supervisor| supervisor["scheduled_restart"].clone()),
+                "last_crash_at": last_crash_at.map(|at| at.to_rfc3339()),
+                "last_crash_cause": last_crash.as_ref().map(|crash| crash["cause"].clone()),
+            },
+            "rcon": match &rcon_health {
+                None => Value::Null,
+                Some(Ok(health)) => json!({
+                    "responding": true,
+                    "latency_ms": milliseconds(health.latency),
+                    "players": health.players,
+                    "tps": health.tick_rate.map(|(ticks_per_second, _)| ticks_per_second),
+                    "mspt": health.tick_rate.and_then(|(_, milliseconds_per_tick)| milliseconds_per_tick),
+                }),
+                Some(Err(err)) => json!({"responding": false, "error": err.to_string()}),
+            },
+            "server_list_ping": match &server_list_ping {
+                Ok(ping) => json!({
+                    "responding": true,
+                    "latency_ms": milliseconds(ping.latency),
+                    "version": ping.version,
+                    "protocol": ping.protocol,
+                    "motd": console::strip_ansi_codes(&render_motd(&ping.description)),
+                    "online_players": ping.online_players,
+                    "max_players": ping.max_players,
+                    "player_sample": ping.player_sample,
+                }),
+                Err(err) => json!({"responding": false, "error": err.to_string()}),
+            },
+            "disk": disk_sizes.map(|(world_size, backups_size)| json!({
+                "world_path": world_path, "world_bytes": world_size, "backups_path": backups_path, "backups_bytes": backups_size,
+            })),
+            "backups": {
+                "fresh": backups_fresh,
+                "max_age_seconds": max_backup_age.map(|max_age| max_age.num_seconds()),
+                "last_success_at": last_backup_at.map(|at| at.to_rfc3339()),
+                "last_success_age_seconds": last_backup_age.map(|age| age.num_seconds()),
+                "daemon_pid": daemon_pid,
+                "next_run_at": next_backup_at.map(|at| at.to_rfc3339()),
+            },
+        });
+        println!("{}", status);
+        return if problems.is_empty() { Ok(()) } else { Err(unhealthy()) };
+    }
+
+    let marker = |healthy: bool| if healthy { style("●").green() } else { style("○").red() };
+    let unknown = || String::from("-");
+    let format_time = |at: DateTime<Local>| at.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let state = match &supervisor {
+        Some(supervisor) => supervisor["state"].as_str().unwrap_or("unknown").to_uppercase(),
+        None if running => String::from("RUNNING (not started by servmgr)"),
+        None => String::from("NOT RUNNING"),
+    };
+    println!("{} {}", marker(running), style(format!("Minecraft server: {}", state)).cyan());
+    if let Some(supervisor) = &supervisor {
+        println!("  {:<18} {}", "PID:", supervisor["server_pid"].as_u64().map(|pid| pid.to_string()).unwrap_or_else(unknown));
+        println!("  {:<18} {}", "Supervisor PID:", supervisor["pid"]);
+        println!("  {:<18} {}", "Started:", server_started_at.map(format_time).unwrap_or_else(unknown));
+        println!("  {:<18} {}", "Uptime:", uptime.map(backup_schedule::format_duration).unwrap_or_else(unknown));
+        println!("  {:<18} {}", "Restarts:", supervisor["restarts"]);
+        println!("  {:<18} {}", "Crashes:", supervisor["crashes"]);
+        if let Some(restart_at) = supervisor["scheduled_restart"]["at"].as_str().and_then(|at| DateTime::parse_from_rfc3339(at).ok()) {
+            println!("  {:<18} {}", "Next restart:", format_time(restart_at.with_timezone(&Local)));
+        }
+        if let Some(restart_at) = supervisor["restart_at"].as_str().and_then(|at| DateTime::parse_from_rfc3339(at).ok()) {
+            println!("  {:<18} {}", "Restarting at:", format_time(restart_at.with_timezone(&Local)));
+        }
+    }
+    if let (Some(crash), Some(at)) = (&last_crash, last_crash_at) {
+        println!("  {:<18} {} ({})", "Last crash:", format_time(at), crash["cause"].as_str().unwrap_or("-"));
+    }
+
+    match &rcon_health {
+        None => {},
+        Some(Ok(health)) => {
+            println!("{} {}", marker(true), style(format!("RCON interface: RESPONDING ({:.1}ms)", milliseconds(health.latency))).cyan());
+            println!("  {:<18} {}", "Players:", if health.players.is_empty() { unknown() } else { health.players.join(", ") });
+            println!("  {:<18} {}", "TPS:", health.tick_rate
+                .map(|(ticks_per_second, milliseconds_per_tick)| match milliseconds_per_tick {
+                    Some(milliseconds_per_tick) => format!("{:.1} ({:.1} ms per tick)", ticks_per_second, milliseconds_per_tick),
+                    None => format!("{:.1}", ticks_per_second),
+                })
+                .unwrap_or_else(unknown));
+        },
+        Some(Err(err)) => println!("{} {} ({})", marker(false), style("RCON interface: NOT RESPONDING").cyan(), err),
+    }
+
+    match &server_list_ping {
+        Ok(ping) => {
+            println!("{} {}", marker(true), style(format!("Server list ping: RESPONDING ({:.1}ms)", milliseconds(ping.latency))).cyan());
+            println!("  {:<18} {} (protocol {})", "Version:", ping.version, ping.protocol);
+            println!("  {:<18} {}", "MOTD:", render_motd(&ping.description).replace('\n', " / "));
+            println!("  {:<18} {}/{}", "Online:", ping.online_players, ping.max_players);
+        },
+        Err(err) => println!("{} {} ({})", marker(false), style("Server list ping: NOT RESPONDING").cyan(), err),
+    }
+
+    if let Some((world_size, backups_size)) = disk_sizes {
+        println!("{} {}", marker(true), style("Disk usage").cyan());
+        println!("  {:<18} {} ({})", "World:", disk_usage::format_size(world_size), world_path.display());
+        println!("  {:<18} {} ({})", "Backups:", disk_usage::format_size(backups_size), backups_path.display());
+    }
+
+    // without a max age having no backups yet isn't a problem
+    let (backups_marker, backups_state) = match (last_backup_age, max_backup_age) {
+        (None, None) => (style("○").dim(), "NONE YET"),
+        _ => (marker(backups_fresh), if backups_fresh { "OK" } else { "STALE" }),
+    };
+    println!("{} {}", backups_marker, style(format!("Backups: {}", backups_state)).cyan());
+    println!("  {:<18} {}", "Last success:", match (last_backup_at, last_backup_age) {
+        (Some(at), Some(age)) => format!("{} ({} ago)", format_time(at), backup_schedule::format_duration(age)),
+        _ => unknown(),
+    });
+    println!("  {:<18} {}", "Backup daemon:", daemon_pid.map(|pid| format!("running (pid {})", pid)).unwrap_or_else(|| String::from("not running")));
+    println!("  {:<18} {}", "Next backup:", next_backup_at.map(format_time).unwrap_or_else(unknown));
+
+    if problems.is_empty() { Ok(()) } else { Err(unhealthy()) }
+}
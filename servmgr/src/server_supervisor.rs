@@ -0,0 +1,619 @@
+use std::{
+    collections::VecDeque, fs, io::{self, BufRead, BufReader, Read, Write}, os::unix::{net::{UnixListener, UnixStream}, process::CommandExt},
+    path::PathBuf, process::{Child, ChildStdin, Command, ExitStatus, Stdio}, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    thread::{self, JoinHandle}, time::{Duration, Instant},
+};
+use chrono::{DateTime, Local};
+use console::style;
+use serde_json::{json, Value};
+use crate::crash_history::{self, Crash};
+use crate::archive::ArchiveFormat;
+use crate::backup_catalog::BackupStorage;
+use crate::restart_schedule::{self, ScheduledRestart};
+use crate::{backup_controller, backup_schedule, config};
+use crate::error::ServmgrError;
+use crate::rcon::RconConfig;
+
+pub const PID_FILE_NAME: &str = "servmgr_server.pid";
+pub const SOCKET_FILE_NAME: &str = "servmgr_server.sock";
+/// The supervisor's own messages along with everything the server writes to its console
+pub const LOG_FILE_NAME: &str = "servmgr_server.log";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the server gets to exit after SIGTERM before it is killed
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Console lines kept to record with a crash
+const CRASH_LOG_TAIL_LINES: usize = 50;
+
+/// What a client asked the supervisor to do with the server
+#[derive(Clone, Copy)]
+enum Request {
+    Stop { timeout: Duration },
+    Restart { timeout: Duration },
+}
+
+/// How the server went down when it was asked to stop
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// It saved and exited on `stop`
+    Exited,
+    /// It didn't exit on `stop` in time and was sent SIGTERM
+    Terminated,
+    /// It didn't exit on SIGTERM either
+    Killed,
+}
+
+impl StopOutcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            StopOutcome::Exited => "exited",
+            StopOutcome::Terminated => "terminated",
+            StopOutcome::Killed => "killed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StopOutcome> {
+        [StopOutcome::Exited, StopOutcome::Terminated, StopOutcome::Killed].into_iter().find(|outcome| outcome.name() == name)
+    }
+}
+
+/// The running server as the supervisor sees it, shared with the socket thread
+struct SupervisorState {
+    started_at: DateTime<Local>,
+    server_pid: Option<u32>,
+    server_started_at: Option<DateTime<Local>>,
+    stdin: Option<ChildStdin>,
+    stopping: bool,
+    restarts: u64,
+    crashes: u64,
+    /// When the server is started again after crashing, `None` unless it is waiting to be
+    restart_at: Option<DateTime<Local>>,
+    scheduled_restart: Option<ScheduledRestart>,
+    /// Daily restarts up to here are done or were cancelled
+    daily_restarts_after: DateTime<Local>,
+    request: Option<Request>,
+    /// Clients waiting on the pending request, each is answered once it finished
+    waiting: Vec<UnixStream>,
+}
+
+impl SupervisorState {
+    fn to_json(&self) -> Value {
+        json!({
+            "pid": std::process::id(),
+            "started_at": self.started_at.to_rfc3339(),
+            "server_pid": self.server_pid,
+            "server_started_at": self.server_started_at.map(|at| at.to_rfc3339()),
+            // a server that ran before and has no process now crashed and is waiting to be started again
+            "state": if self.stopping { "stopping" } else if self.server_pid.is_some() { "running" }
+                else if self.server_started_at.is_some() { "restarting" } else { "starting" },
+            "restarts": self.restarts,
+            "crashes": self.crashes,
+            "restart_at": self.restart_at.map(|at| at.to_rfc3339()),
+            "scheduled_restart": self.scheduled_restart.as_ref().map(ScheduledRestart::to_json),
+        })
+    }
+
+    /// Schedules the next of restart.times unless a restart is scheduled already
+    fn schedule_daily_restart(&mut self) {
+        if self.scheduled_restart.is_none() {
+            self.scheduled_restart = restart_schedule::next_daily_restart(self.daily_restarts_after);
+        }
+    }
+
+    /// Answers every client waiting on the request that just finished
+    fn answer_waiting(&mut self, response: &Value) {
+        for mut client in self.waiting.drain(..) {
+            let _ = writeln!(client, "{}", response);
+        }
+    }
+}
+
+pub fn supervisor_file_path(file_name: &str) -> PathBuf {
+    config::get().server_directory.join(file_name)
+}
+
+fn try_lock_file(pid_file: fs::File) -> io::Result<Option<fs::File>> {
+    match pid_file.try_lock() {
+        Ok(()) => Ok(Some(pid_file)),
+        Err(fs::TryLockError::WouldBlock) => Ok(None),
+        Err(fs::TryLockError::Error(err)) => Err(err),
+    }
+}
+
+/// The PID of the server the supervisor runs, `None` if no supervisor holds the PID file's lock (the supervisor holds
+/// it for as long as it runs, so it can't go stale) or the server isn't running under it right now (starting, or
+/// waiting to be started again after a crash)
+pub fn running_server_pid() -> io::Result<Option<i32>> {
+    let mut pid_file = match fs::File::open(supervisor_file_path(PID_FILE_NAME)) {
+        Ok(pid_file) => pid_file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut pid = String::new();
+    pid_file.read_to_string(&mut pid)?;
+    if try_lock_file(pid_file)?.is_some() { return Ok(None); }
+
+    // empty until the server has been spawned and again once it exited
+    Ok(pid.trim().parse().ok())
+}
+
+/// Whether a supervisor is running, whether or not its server is up yet
+pub fn is_supervisor_running() -> bool {
+    fs::File::open(supervisor_file_path(PID_FILE_NAME)).ok()
+        .is_some_and(|pid_file| matches!(try_lock_file(pid_file), Ok(None)))
+}
+
+/// Sends a request to the supervisor, the connection is handed back to read the response from
+fn send_request(request: &Value) -> io::Result<UnixStream> {
+    let mut stream = UnixStream::connect(supervisor_file_path(SOCKET_FILE_NAME))?;
+    writeln!(stream, "{}", request)?;
+    Ok(stream)
+}
+
+fn read_response(stream: UnixStream) -> io::Result<Value> {
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    if response.is_empty() { return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "The server supervisor exited without answering")); }
+    let response: Value = serde_json::from_str(&response).map_err(io::Error::other)?;
+    match response["error"].as_str() {
+        Some(error) => Err(io::Error::other(error)),
+        None => Ok(response),
+    }
+}
+
+/// The supervisor's status, `None` if it isn't running
+pub fn read_supervisor_status() -> io::Result<Option<Value>> {
+    let stream = match send_request(&json!({"request": "status"})) {
+        Ok(stream) => stream,
+        Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    read_response(stream).map(Some)
+}
+
+/// Writes a line to the server's console as if it was typed in
+pub fn send_console_input(line: &str) -> io::Result<()> {
+    let stream = send_request(&json!({"request": "input", "line": line}))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    read_response(stream).map(|_| ())
+}
+
+/// Has the supervisor stop the server, returning once it is down, along with how it went down
+pub fn request_stop(timeout: Duration) -> io::Result<Value> {
+    read_response(send_request(&json!({"request": "stop", "timeout_secs": timeout.as_secs()}))?)
+}
+
+/// Has the supervisor stop the server and start it again, returning once the new server is running
+pub fn request_restart(timeout: Duration) -> io::Result<Value> {
+    read_response(send_request(&json!({"request": "restart", "timeout_secs": timeout.as_secs()}))?)
+}
+
+/// Has the supervisor restart the server after `delay`, counting down to it in chat, in place of any restart scheduled
+/// already, the answer holds the restart's time
+pub fn request_scheduled_restart(delay: Duration, backup: bool) -> io::Result<Value> {
+    let stream = send_request(&json!({"request": "schedule_restart", "in_secs": delay.as_secs(), "backup": backup}))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    read_response(stream)
+}
+
+/// Cancels the scheduled restart, the answer holds the cancelled restart and the next daily one taking its place
+pub fn request_restart_cancellation() -> io::Result<Value> {
+    let stream = send_request(&json!({"request": "cancel_restart"}))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    read_response(stream)
+}
+
+/// Answers status and console input requests straight away, stop and restart requests are left for the main loop
+fn serve_requests(listener: UnixListener, state: Arc<Mutex<SupervisorState>>) {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue; };
+        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+        let mut request = String::new();
+        let Ok(reader) = stream.try_clone() else { continue; };
+        if BufReader::new(reader).read_line(&mut request).is_err() { continue; }
+        let Ok(request) = serde_json::from_str::<Value>(&request) else {
+            let _ = writeln!(stream, "{}", json!({"error": "Malformed request"}));
+            continue;
+        };
+
+        let mut state = state.lock().unwrap();
+        let mut cancellation_announced = false;
+        let timeout = Duration::from_secs(request["timeout_secs"].as_u64().unwrap_or_else(|| config::get().stop_timeout.num_seconds() as u64));
+        let response = match request["request"].as_str() {
+            Some("status") => state.to_json(),
+            Some("input") => match (state.stdin.as_mut(), request["line"].as_str()) {
+                (Some(stdin), Some(line)) => match writeln!(stdin, "{}", line).and_then(|_| stdin.flush()) {
+                    Ok(()) => json!({}),
+                    Err(err) => json!({"error": format!("Failed to write to the server's console ({})", err)}),
+                },
+                (None, _) => json!({"error": "The server isn't running"}),
+                (_, None) => json!({"error": "Malformed request"}),
+            },
+            Some("schedule_restart") => match request["in_secs"].as_i64()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|delay| Local::now().checked_add_signed(delay)) {
+                Some(at) => {
+                    let restart = ScheduledRestart::new(at, request["backup"] == true, false);
+                    let response = restart.to_json();
+                    println!("{}: Scheduled a restart at {}", style("[INFO]").cyan(), restart.at.format("%Y-%m-%d %H:%M:%S"));
+                    state.scheduled_restart = Some(restart);
+                    response
+                },
+                None => json!({"error": "The restart can't be scheduled that far ahead"}),
+            },
+            Some("cancel_restart") => match state.scheduled_restart.take() {
+                Some(restart) => {
+                    if restart.daily { state.daily_restarts_after = restart.at; }
+                    cancellation_announced = restart.warned();
+                    state.schedule_daily_restart();
+                    println!("{}: Cancelled the restart at {}", style("[INFO]").cyan(), restart.at.format("%Y-%m-%d %H:%M:%S"));
+                    json!({"cancelled": restart.to_json(), "next": state.scheduled_restart.as_ref().map(ScheduledRestart::to_json)})
+                },
+                None => json!({"error": "No restart is scheduled"}),
+            },
+            Some("stop") | Some("restart") => {
+                // a second request while one is pending waits for the first one to finish
+                if state.request.is_none() && !state.stopping {
+                    state.request = Some(if request["request"] == "stop" { Request::Stop { timeout } } else { Request::Restart { timeout } });
+                }
+                state.waiting.push(stream);
+                continue;
+            },
+            _ => json!({"error": "Unknown request"}),
+        };
+        drop(state);
+        let _ = writeln!(stream, "{}", response);
+        if cancellation_announced && let Err(err) = restart_schedule::broadcast(&mut None, "The scheduled restart was cancelled", false) {
+            eprintln!("{}: Failed to tell players the restart was cancelled ({})", style("[ERROR]").red(), err);
+        }
+    }
+}
+
+/// Warns players as the scheduled restart comes closer, then has the main loop restart the server (backing the world
+/// up first if the restart asks for it), schedules the next daily restart whenever none is scheduled
+fn run_restart_schedule(state: Arc<Mutex<SupervisorState>>) {
+    let stop_timeout = config::get().stop_timeout.to_std().unwrap_or_default();
+    // one RCON session for all of a countdown's warnings
+    let mut rcon = None;
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let now = Local::now();
+        let mut guard = state.lock().unwrap();
+        guard.schedule_daily_restart();
+        let Some(restart) = guard.scheduled_restart.as_mut() else { rcon = None; continue; };
+
+        if restart.at > now {
+            let warning = restart.due_warning(now);
+            drop(guard);
+            let message = warning.map(|seconds| (format!("The server restarts in {}", restart_schedule::describe_time_left(seconds)), seconds <= 10));
+            if let Some((message, urgent)) = message && let Err(err) = restart_schedule::broadcast(&mut rcon, &message, urgent) {
+                eprintln!("{}: Failed to warn players of the restart ({})", style("[ERROR]").red(), err);
+            }
+            continue;
+        }
+
+        let restart = guard.scheduled_restart.take().expect("checked above");
+        if restart.daily { guard.daily_restarts_after = restart.at; }
+        let running = guard.server_pid.is_some() && !guard.stopping && guard.request.is_none();
+        drop(guard);
+        if !running {
+            println!("{}: Skipped the restart at {}, the server isn't running", style("[INFO]").cyan(), restart.at.format("%Y-%m-%d %H:%M:%S"));
+            continue;
+        }
+
+        println!("{}: Restarting the Minecraft server as scheduled", style("[INFO]").cyan());
+        if restart.backup {
+            let options = backup_controller::BackupOptions {
+                storage: BackupStorage::Archive(ArchiveFormat::TarGz), level: None, schedule: Some("restart"),
+                save_timeout: backup_controller::DEFAULT_SAVE_TIMEOUT, notifications: None,
+            };
+            // the restart goes ahead without a backup, it doesn't put the world at risk
+            let backup_result = RconConfig::load(None)
+                .map_err(|err| ServmgrError::Config(format!("Failed to load the RCON settings ({})", err)))
+                .and_then(|rcon_config| backup_controller::backup_minecraft_server(&rcon_config.password, &options));
+            match backup_result {
+                Ok(outcome) => println!("{}: Backed up the world to {} before restarting", style("[INFO]").cyan(), outcome.name),
+                Err(err) => eprintln!("{}: Failed to back up the world before restarting ({})", style("[ERROR]").red(), err.summary()),
+            }
+        }
+
+        // only once the backup is done, it can take minutes
+        if let Err(err) = restart_schedule::broadcast(&mut rcon, "The server is restarting now", true) {
+            eprintln!("{}: Failed to warn players of the restart ({})", style("[ERROR]").red(), err);
+        }
+        rcon = None; // the server closes it as it stops
+
+        let mut guard = state.lock().unwrap();
+        if guard.request.is_none() && !guard.stopping { guard.request = Some(Request::Restart { timeout: stop_timeout }); }
+    }
+}
+
+/// The last lines the server wrote to its console, across stdout and stderr
+type ConsoleTail = Arc<Mutex<VecDeque<String>>>;
+
+/// The running server along with the threads copying its console output
+struct Server {
+    process: Child,
+    started_at: DateTime<Local>,
+    output: Vec<JoinHandle<()>>,
+}
+
+/// Copies what the server writes to its console into the supervisor's output, line by line, keeping the last lines in
+/// `console_tail`, the thread finishes once the server has exited
+fn forward_output(output: impl Read + Send + 'static, console_tail: &ConsoleTail) -> JoinHandle<()> {
+    let console_tail = Arc::clone(console_tail);
+    thread::spawn(move || {
+        let mut output = BufReader::new(output);
+        let mut line = Vec::new();
+        while matches!(output.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            let text = String::from_utf8_lossy(&line);
+            print!("{}", text);
+            let _ = io::stdout().flush();
+
+            let mut console_tail = console_tail.lock().unwrap();
+            if console_tail.len() == CRASH_LOG_TAIL_LINES { console_tail.pop_front(); }
+            console_tail.push_back(String::from(text.trim_end()));
+            line.clear();
+        }
+    })
+}
+
+/// Starts the java server with the configured command line in the server directory
+fn spawn_server(state: &Mutex<SupervisorState>, console_tail: &ConsoleTail) -> Result<Server, ServmgrError> {
+    let config = config::get();
+    let command_line = config.jvm.command_line();
+    let mut server = Command::new(&command_line[0])
+        .args(&command_line[1..])
+        .current_dir(&config.server_directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0) // a Ctrl-C meant for the supervisor doesn't reach the server, which is stopped with `stop` instead
+        .spawn()
+        .map_err(ServmgrError::process(&config.jvm.java))?;
+
+    console_tail.lock().unwrap().clear();
+    let output = [server.stdout.take().map(|stdout| forward_output(stdout, console_tail)),
+        server.stderr.take().map(|stderr| forward_output(stderr, console_tail))];
+
+    let pid_path = supervisor_file_path(PID_FILE_NAME);
+    fs::write(&pid_path, server.id().to_string()).map_err(ServmgrError::filesystem("write", &pid_path))?;
+
+    let started_at = Local::now();
+    let mut state = state.lock().unwrap();
+    state.server_pid = Some(server.id());
+    state.server_started_at = Some(started_at);
+    state.stdin = server.stdin.take();
+    state.stopping = false;
+    state.restart_at = None;
+    println!("{}: Started the Minecraft server (pid {}): {}", style("[INFO]").cyan(), server.id(), command_line.join(" "));
+    Ok(Server { process: server, started_at, output: output.into_iter().flatten().collect() })
+}
+
+/// Forgets the server process once it has been reaped, its PID could be reused by any other process from then on so
+/// the PID file `status` and `stop` go by is emptied along with it
+fn clear_server_pid(state: &Mutex<SupervisorState>) {
+    let mut state = state.lock().unwrap();
+    state.server_pid = None;
+    state.stdin = None;
+    let pid_path = supervisor_file_path(PID_FILE_NAME);
+    if let Err(err) = fs::write(&pid_path, "") {
+        eprintln!("{}: Failed to clear {} ({})", style("[ERROR]").red(), pid_path.display(), err);
+    }
+}
+
+/// Waits up to `timeout` for the server to exit, `None` if it is still running
+fn wait_for_exit(server: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let started_at = Instant::now();
+    loop {
+        if let Some(status) = server.try_wait()? { return Ok(Some(status)); }
+        if started_at.elapsed() >= timeout { return Ok(None); }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Sends `stop`, which has the server save every world before it exits, the server is only sent SIGTERM (and then
+/// SIGKILL) if it hasn't exited within `timeout`
+fn stop_server(server: &mut Child, state: &Mutex<SupervisorState>, timeout: Duration) -> io::Result<(StopOutcome, ExitStatus)> {
+    let stdin = {
+        let mut state = state.lock().unwrap();
+        state.stopping = true;
+        state.stdin.take()
+    };
+    println!("{}: Stopping the Minecraft server (waiting up to {}s for it to save and exit)", style("[INFO]").cyan(), timeout.as_secs());
+    if let Some(mut stdin) = stdin
+        && let Err(err) = writeln!(stdin, "stop").and_then(|_| stdin.flush()) {
+        eprintln!("{}: Failed to send stop to the server's console ({})", style("[ERROR]").red(), err);
+    }
+    if let Some(status) = wait_for_exit(server, timeout)? { return Ok((StopOutcome::Exited, status)); }
+
+    eprintln!("{}: The server didn't stop within {}s, sending SIGTERM", style("[ERROR]").red(), timeout.as_secs());
+    // SAFETY: kill only sends a signal, the PID is of our own child which hasn't been reaped yet
+    unsafe { libc::kill(server.id() as i32, libc::SIGTERM) };
+    if let Some(status) = wait_for_exit(server, TERMINATE_TIMEOUT)? { return Ok((StopOutcome::Terminated, status)); }
+
+    eprintln!("{}: The server didn't exit within {}s of SIGTERM, killing it", style("[ERROR]").red(), TERMINATE_TIMEOUT.as_secs());
+    server.kill()?;
+    Ok((StopOutcome::Killed, server.wait()?))
+}
+
+/// Runs the server until it exits, is asked to stop over the socket or the supervisor receives SIGINT or SIGTERM (which
+/// stop the server the same way), lines typed into the supervisor's stdin go to the server's console
+pub fn run_server_supervisor() -> Result<(), ServmgrError> {
+    let pid_path = supervisor_file_path(PID_FILE_NAME);
+    let pid_file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&pid_path)
+        .and_then(try_lock_file)
+        .map_err(ServmgrError::filesystem("lock", &pid_path))?
+        .ok_or_else(|| ServmgrError::Invalid(String::from("The Minecraft server is already running under servmgr")))?;
+    pid_file.set_len(0).map_err(ServmgrError::filesystem("write", &pid_path))?;
+
+    let terminate_requested = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&terminate_requested))
+            .map_err(|err| ServmgrError::Failed(format!("Failed to install the signal handler ({})", err)))?;
+    }
+
+    // the socket can only be stale here since the PID file lock is ours
+    let socket_path = supervisor_file_path(SOCKET_FILE_NAME);
+    let _ = fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path).map_err(ServmgrError::filesystem("open the control socket", &socket_path))?;
+
+    let state = Arc::new(Mutex::new(SupervisorState {
+        started_at: Local::now(), server_pid: None, server_started_at: None, stdin: None, stopping: false, restarts: 0, crashes: 0,
+        restart_at: None, scheduled_restart: None, daily_restarts_after: Local::now(), request: None, waiting: Vec::new(),
+    }));
+    state.lock().unwrap().schedule_daily_restart();
+    let socket_state = Arc::clone(&state);
+    thread::spawn(move || serve_requests(listener, socket_state));
+    let schedule_state = Arc::clone(&state);
+    thread::spawn(move || run_restart_schedule(schedule_state));
+
+    let input_state = Arc::clone(&state);
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break; };
+            if let Some(stdin) = input_state.lock().unwrap().stdin.as_mut() { let _ = writeln!(stdin, "{}", line); }
+        }
+    });
+
+    println!("{}: Server supervisor started (pid {})", style("[INFO]").cyan(), std::process::id());
+    let result = supervise(&state, &terminate_requested);
+
+    let mut state = state.lock().unwrap();
+    if let Err(err) = &result { state.answer_waiting(&json!({"error": err.summary()})); }
+    let _ = fs::remove_file(&socket_path);
+    let _ = fs::remove_file(&pid_path);
+    drop(pid_file);
+    println!("{}", style("[INFO] Server supervisor stopped").cyan());
+    result
+}
+
+/// Records a crash and works out whether the server is started again, returning how long to wait first, or the error
+/// the supervisor exits with when the server crashed too often within crash.window (or crash.restart is off)
+fn handle_crash(server: Server, status: ExitStatus, console_tail: &ConsoleTail, crash_times: &mut VecDeque<DateTime<Local>>)
+    -> Result<chrono::Duration, ServmgrError> {
+    let settings = &config::get().crash;
+    // the output threads finish with the server's last lines once its ends of the pipes close
+    for output in server.output { let _ = output.join(); }
+    let crashed_at = Local::now();
+    let crash = Crash {
+        at: crashed_at,
+        status,
+        uptime: crashed_at - server.started_at,
+        log_tail: console_tail.lock().unwrap().iter().cloned().collect(),
+        crash_report: crash_history::find_crash_report(server.started_at),
+    };
+
+    crash_times.push_back(crashed_at);
+    while crash_times.front().is_some_and(|at| crashed_at - *at > settings.window) { crash_times.pop_front(); }
+    let restart_delay = (settings.restart && crash_times.len() <= settings.max_restarts)
+        .then(|| settings.restart_delay(crash_times.len()));
+
+    let cause = crash.cause();
+    eprintln!("{}: The Minecraft server crashed after {} ({}): {}",
+        style("[ERROR]").red(), backup_schedule::format_duration(crash.uptime), status, cause);
+    if let Some(crash_report) = &crash.crash_report {
+        eprintln!("{}: The server wrote a crash report to {}", style("[ERROR]").red(), crash_report.display());
+    }
+    if let Err(err) = crash_history::record_crash(&crash, restart_delay) {
+        eprintln!("{}: Failed to record the crash in {} ({})", style("[ERROR]").red(), crash_history::crash_history_path().display(), err);
+    }
+
+    match restart_delay {
+        Some(delay) => {
+            println!("{}: Restarting the Minecraft server in {} (crash {} of {} allowed within {})", style("[INFO]").cyan(),
+                backup_schedule::format_duration(delay), crash_times.len(), settings.max_restarts, backup_schedule::format_duration(settings.window));
+            Ok(delay)
+        },
+        None if !settings.restart => Err(ServmgrError::Failed(format!("The Minecraft server crashed ({}), \
+            it was left stopped since crash.restart is off", cause))),
+        None => Err(ServmgrError::Failed(format!("The Minecraft server crashed {} times within {} ({}), it was left stopped",
+            crash_times.len(), backup_schedule::format_duration(settings.window), cause))),
+    }
+}
+
+/// Waits out the delay before starting the server again after a crash, `false` if the supervisor was asked to stop in
+/// the meantime, a restart request cuts the delay short
+fn wait_to_restart(state: &Mutex<SupervisorState>, terminate_requested: &AtomicBool, delay: chrono::Duration, status: ExitStatus) -> bool {
+    let restart_at = Local::now() + delay;
+    state.lock().unwrap().restart_at = Some(restart_at);
+    while Local::now() < restart_at {
+        if terminate_requested.load(Ordering::Relaxed) { return false; }
+        let mut state = state.lock().unwrap();
+        match state.request.take() {
+            Some(Request::Stop { .. }) => {
+                state.restart_at = None;
+                state.answer_waiting(&json!({"outcome": StopOutcome::Exited.name(), "exit_code": status.code(), "seconds": 0.0}));
+                return false;
+            },
+            Some(Request::Restart { .. }) => return true,
+            None => {},
+        }
+        drop(state);
+        thread::sleep(POLL_INTERVAL);
+    }
+    true
+}
+
+fn supervise(state: &Mutex<SupervisorState>, terminate_requested: &AtomicBool) -> Result<(), ServmgrError> {
+    let stop_timeout = config::get().stop_timeout.to_std().unwrap_or_default();
+    let console_tail = ConsoleTail::default();
+    let mut crash_times = VecDeque::new();
+    let mut server = spawn_server(state, &console_tail)?;
+    loop {
+        if let Some(status) = server.process.try_wait().map_err(ServmgrError::process(&config::get().jvm.java))? {
+            clear_server_pid(state);
+            // vanilla exits with 0 after writing a crash report, so a new report is as telling as the exit status
+            let crashed = !status.success() || crash_history::find_crash_report(server.started_at).is_some();
+            if !crashed {
+                println!("{}: The Minecraft server exited ({})", style("[INFO]").cyan(), status);
+                return Ok(());
+            }
+
+            state.lock().unwrap().crashes += 1;
+            let delay = handle_crash(server, status, &console_tail, &mut crash_times)?;
+            if !wait_to_restart(state, terminate_requested, delay, status) { return Ok(()); }
+            server = spawn_server(state, &console_tail)?;
+            let mut state = state.lock().unwrap();
+            state.restarts += 1;
+            // answers a restart request that cut the delay short
+            state.answer_waiting(&json!({"outcome": StopOutcome::Exited.name(), "exit_code": status.code(), "seconds": 0.0,
+                "server_pid": server.process.id()}));
+            continue;
+        }
+
+        let request = match terminate_requested.load(Ordering::Relaxed) {
+            true => Some(Request::Stop { timeout: stop_timeout }),
+            false => state.lock().unwrap().request.take(),
+        };
+        let Some(request) = request else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        let (timeout, restart) = match request {
+            Request::Stop { timeout } => (timeout, false),
+            Request::Restart { timeout } => (timeout, true),
+        };
+        let stopping_at = Instant::now();
+        let (outcome, status) = stop_server(&mut server.process, state, timeout).map_err(ServmgrError::process(&config::get().jvm.java))?;
+        let stopped_in = stopping_at.elapsed().as_secs_f64();
+        println!("{}: The Minecraft server {} in {:.1}s ({})", style("[INFO]").cyan(), outcome.name(), stopped_in, status);
+        clear_server_pid(state);
+        {
+            let mut state = state.lock().unwrap();
+            let response = json!({"outcome": outcome.name(), "exit_code": status.code(), "seconds": stopped_in});
+            if !restart {
+                state.answer_waiting(&response);
+                return Ok(());
+            }
+            state.restarts += 1;
+        }
+
+        server = spawn_server(state, &console_tail)?;
+        let mut state = state.lock().unwrap();
+        let response = json!({"outcome": outcome.name(), "exit_code": status.code(), "seconds": stopped_in, "server_pid": server.process.id()});
+        state.answer_waiting(&response);
+    }
+}
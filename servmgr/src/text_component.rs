@@ -0,0 +1,157 @@
+use console::Style;
+use serde_json::Value;
+
+// minecraft's 16 chat colors, indexed by their legacy formatting code (§0 - §f)
+const NAMED_COLORS: [(&str, (u8, u8, u8)); 16] = [
+    ("black", (0x00, 0x00, 0x00)),
+    ("dark_blue", (0x00, 0x00, 0xAA)),
+    ("dark_green", (0x00, 0xAA, 0x00)),
+    ("dark_aqua", (0x00, 0xAA, 0xAA)),
+    ("dark_red", (0xAA, 0x00, 0x00)),
+    ("dark_purple", (0xAA, 0x00, 0xAA)),
+    ("gold", (0xFF, 0xAA, 0x00)),
+    ("gray", (0xAA, 0xAA, 0xAA)),
+    ("dark_gray", (0x55, 0x55, 0x55)),
+    ("blue", (0x55, 0x55, 0xFF)),
+    ("green", (0x55, 0xFF, 0x55)),
+    ("aqua", (0x55, 0xFF, 0xFF)),
+    ("red", (0xFF, 0x55, 0x55)),
+    ("light_purple", (0xFF, 0x55, 0xFF)),
+    ("yellow", (0xFF, 0xFF, 0x55)),
+    ("white", (0xFF, 0xFF, 0xFF)),
+];
+
+pub fn is_named_color(name: &str) -> bool {
+    NAMED_COLORS.iter().any(|(color_name, _)| *color_name == name)
+}
+
+#[derive(Clone, Default)]
+struct Formatting {
+    color: Option<(u8, u8, u8)>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+}
+
+impl Formatting {
+    fn style(&self) -> Style {
+        let mut style = Style::new();
+        if let Some((r, g, b)) = self.color { style = style.color256(to_ansi256(r, g, b)); }
+        if self.bold { style = style.bold(); }
+        if self.italic { style = style.italic(); }
+        if self.underlined { style = style.underlined(); }
+        if self.strikethrough { style = style.strikethrough(); }
+        style
+    }
+}
+
+/// Renders an RCON response for the terminal, treating it as a JSON text component when it parses as one and as
+/// plain text with legacy `§` formatting codes otherwise
+pub fn render_response(response: &str) -> String {
+    let trimmed = response.trim();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && let Ok(component) = serde_json::from_str::<Value>(trimmed) {
+        let mut rendered = String::new();
+        render_component(&component, &Formatting::default(), &mut rendered);
+        return rendered;
+    }
+
+    render_legacy_text(response)
+}
+
+fn render_component(component: &Value, inherited: &Formatting, rendered: &mut String) {
+    match component {
+        Value::String(text) => rendered.push_str(&inherited.style().apply_to(text).to_string()),
+        Value::Array(children) => {
+            // the first element of an array is the parent of the ones after it
+            let Some((parent, siblings)) = children.split_first() else { return; };
+            let formatting = apply_formatting(parent, inherited);
+            render_component(parent, inherited, rendered);
+            for sibling in siblings { render_component(sibling, &formatting, rendered); }
+        },
+        Value::Object(fields) => {
+            let formatting = apply_formatting(component, inherited);
+            let text = match (fields.get("text"), fields.get("translate")) {
+                (Some(Value::String(text)), _) => text.clone(),
+                (_, Some(Value::String(key))) => key.clone(),
+                _ => String::new(),
+            };
+            rendered.push_str(&formatting.style().apply_to(text).to_string());
+
+            if let Some(Value::Array(extra)) = fields.get("extra") {
+                for child in extra { render_component(child, &formatting, rendered); }
+            }
+        },
+        Value::Number(number) => rendered.push_str(&inherited.style().apply_to(number).to_string()),
+        Value::Bool(value) => rendered.push_str(&inherited.style().apply_to(value).to_string()),
+        Value::Null => {},
+    }
+}
+
+fn apply_formatting(component: &Value, inherited: &Formatting) -> Formatting {
+    let mut formatting = inherited.clone();
+    let Value::Object(fields) = component else { return formatting; };
+
+    if let Some(Value::String(color)) = fields.get("color") {
+        formatting.color = parse_color(color).or(formatting.color);
+    }
+    if let Some(Value::Bool(bold)) = fields.get("bold") { formatting.bold = *bold; }
+    if let Some(Value::Bool(italic)) = fields.get("italic") { formatting.italic = *italic; }
+    if let Some(Value::Bool(underlined)) = fields.get("underlined") { formatting.underlined = *underlined; }
+    if let Some(Value::Bool(strikethrough)) = fields.get("strikethrough") { formatting.strikethrough = *strikethrough; }
+
+    formatting
+}
+
+fn render_legacy_text(text: &str) -> String {
+    let mut rendered = String::new();
+    let mut formatting = Formatting::default();
+    let mut segment = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '§' {
+            segment.push(c);
+            continue;
+        }
+
+        let Some(code) = chars.next() else { break; };
+        rendered.push_str(&formatting.style().apply_to(&segment).to_string());
+        segment.clear();
+
+        match code.to_ascii_lowercase() {
+            code @ ('0'..='9' | 'a'..='f') => {
+                let index = code.to_digit(16).unwrap() as usize;
+                // colors reset any formatting that came before them
+                formatting = Formatting { color: Some(NAMED_COLORS[index].1), ..Formatting::default() };
+            },
+            'l' => formatting.bold = true,
+            'o' => formatting.italic = true,
+            'n' => formatting.underlined = true,
+            'm' => formatting.strikethrough = true,
+            'r' => formatting = Formatting::default(),
+            _ => {},
+        }
+    }
+
+    rendered.push_str(&formatting.style().apply_to(&segment).to_string());
+    rendered
+}
+
+fn parse_color(color: &str) -> Option<(u8, u8, u8)> {
+    if let Some(hex) = color.strip_prefix('#') {
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        return Some(((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+
+    NAMED_COLORS.iter()
+        .find(|(name, _)| *name == color)
+        .map(|(_, rgb)| *rgb)
+}
+
+/// Maps an RGB color onto the closest entry of the 6x6x6 cube in the 256 color palette
+fn to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let scale = |channel: u8| ((channel as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * scale(r) + 6 * scale(g) + scale(b)
+}
@@ -0,0 +1,268 @@
+use std::{fs, io::{self, Read, Write}, path::PathBuf};
+use chrono::{DateTime, Local, TimeZone};
+use console::style;
+use serde_json::{json, Value};
+use crate::{config, disk_usage, nbt, server_activator, server_properties::{self, ServerProperties}};
+use crate::error::ServmgrError;
+
+pub const WORLDS_DIRECTORY_NAME: &str = "worlds";
+const LEGACY_WORLD_NAME: &str = "world";
+
+// generation settings for worlds that have been created but not generated by the server yet
+const PENDING_SETTINGS_FILE_NAME: &str = ".servmgr_world.json";
+/// The server.properties generation settings a world is created with unless it asks for others
+const DEFAULT_GENERATION_SETTINGS: [(&str, &str); 3] = [("level-seed", ""), ("level-type", "minecraft:normal"), ("generator-settings", "{}")];
+
+/// What servmgr reads out of a world's `level.dat`
+pub struct LevelMetadata {
+    pub level_name: Option<String>,
+    pub seed: Option<i64>,
+    pub last_played: Option<DateTime<Local>>,
+    pub game_version: Option<String>,
+    pub data_version: Option<i64>,
+    pub hardcore: Option<bool>,
+}
+
+pub fn read_level_metadata(reader: impl Read) -> io::Result<LevelMetadata> {
+    let root = nbt::read_gzip_nbt(reader)?;
+    let data = root.get_path(&["Data"])
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "level.dat has no Data compound"))?;
+
+    Ok(LevelMetadata {
+        level_name: data.get_path(&["LevelName"]).and_then(|tag| tag.as_str()).map(String::from),
+        // the seed moved into WorldGenSettings in 1.16
+        seed: data.get_path(&["WorldGenSettings", "seed"]).or_else(|| data.get_path(&["RandomSeed"])).and_then(|tag| tag.as_i64()),
+        last_played: data.get_path(&["LastPlayed"]).and_then(|tag| tag.as_i64())
+            .and_then(|millis| Local.timestamp_millis_opt(millis).single()),
+        game_version: data.get_path(&["Version", "Name"]).and_then(|tag| tag.as_str()).map(String::from),
+        data_version: data.get_path(&["DataVersion"]).and_then(|tag| tag.as_i64()),
+        hardcore: data.get_path(&["hardcore"]).and_then(|tag| tag.as_i64()).map(|value| value != 0),
+    })
+}
+
+pub fn worlds_directory() -> PathBuf {
+    config::get().server_directory.join(WORLDS_DIRECTORY_NAME)
+}
+
+/// The `level-name` the server will load, relative to the server directory
+fn active_level_name() -> String {
+    ServerProperties::load_default().ok()
+        .and_then(|properties| properties.get("level-name"))
+        .filter(|level_name| !level_name.trim().is_empty())
+        .unwrap_or_else(|| String::from(LEGACY_WORLD_NAME))
+}
+
+/// The world backups are taken of and restored to, server.world in servmgr.toml or else the `level-name` world
+pub fn active_world_path() -> PathBuf {
+    let config = config::get();
+    config.server_directory.join(config.world.clone().unwrap_or_else(active_level_name))
+}
+
+fn managed_level_name(world_name: &str) -> String {
+    format!("{}/{}", WORLDS_DIRECTORY_NAME, world_name)
+}
+
+fn is_valid_world_name(world_name: &str) -> bool {
+    !world_name.is_empty()
+        && !world_name.starts_with('.')
+        && world_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn managed_world_path(world_name: &str) -> Result<PathBuf, ServmgrError> {
+    if !is_valid_world_name(world_name) {
+        return Err(ServmgrError::Invalid(format!("Invalid world name '{}' (use letters, numbers, '-', '_' and '.')", world_name)));
+    }
+
+    Ok(worlds_directory().join(world_name))
+}
+
+fn existing_world_path(world_name: &str) -> Result<PathBuf, ServmgrError> {
+    let world_path = managed_world_path(world_name)?;
+    if !world_path.is_dir() {
+        return Err(ServmgrError::Invalid(format!("World '{}' does not exist in {}", world_name, worlds_directory().display())));
+    }
+
+    Ok(world_path)
+}
+
+fn require_server_stopped(action: &str) -> Result<(), ServmgrError> {
+    if server_activator::is_minecraft_server_running() {
+        return Err(ServmgrError::Invalid(format!("The Minecraft server must be stopped to {} (run 'servmgr stop' first)", action)));
+    }
+    Ok(())
+}
+
+pub fn list_worlds() -> Result<(), ServmgrError> {
+    let active_level_name = active_level_name();
+    let mut worlds: Vec<(String, PathBuf)> = match fs::read_dir(worlds_directory()) {
+        Ok(entries) => entries.filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| (entry.file_name().to_string_lossy().into_owned(), entry.path()))
+            .collect(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(ServmgrError::filesystem("read", &worlds_directory())(err)),
+    };
+    worlds.sort();
+
+    // the world from before worlds were managed still shows up while it is the one being loaded
+    if !active_level_name.starts_with(&format!("{}/", WORLDS_DIRECTORY_NAME)) {
+        worlds.insert(0, (active_level_name.clone(), active_world_path()));
+    }
+
+    if worlds.is_empty() {
+        println!("{}: No worlds found, create one with 'servmgr worlds create <name>'", style("[INFO]").cyan());
+        return Ok(());
+    }
+
+    println!("{}", style(format!("  {:<24} {:>10}  {:<19}  {}", "NAME", "SIZE", "LAST PLAYED", "VERSION")).bold());
+    for (world_name, world_path) in worlds {
+        let level_name = if world_path.starts_with(worlds_directory()) { managed_level_name(&world_name) } else { world_name.clone() };
+        let metadata = fs::File::open(world_path.join("level.dat")).ok().and_then(|file| read_level_metadata(file).ok());
+        let last_played = metadata.as_ref().and_then(|metadata| metadata.last_played)
+            .map(|last_played| last_played.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| String::from("never generated"));
+        let version = metadata.and_then(|metadata| metadata.game_version).unwrap_or_else(|| String::from("-"));
+        let marker = if level_name == active_level_name { style("*").green().to_string() } else { String::from(" ") };
+
+        println!("{} {:<24} {:>10}  {:<19}  {}",
+            marker, world_name, disk_usage::format_size(disk_usage::directory_size(&world_path)), last_played, version);
+    }
+
+    Ok(())
+}
+
+pub fn create_world(world_name: &str, seed: Option<&str>, level_type: Option<&str>, generator_settings: Option<&str>, select: bool) -> Result<(), ServmgrError> {
+    let world_path = managed_world_path(world_name)?;
+    if world_path.exists() {
+        return Err(ServmgrError::Invalid(format!("World '{}' already exists", world_name)));
+    }
+
+    // the server only knows level types by their namespaced id
+    let level_type = level_type.map(|level_type| {
+        if level_type.contains(':') { level_type.to_string() } else { format!("minecraft:{}", level_type.to_lowercase()) }
+    });
+
+    if let Some(generator_settings) = generator_settings
+        && serde_json::from_str::<Value>(generator_settings).is_err() {
+        return Err(ServmgrError::Invalid(String::from("Generator settings must be valid JSON")));
+    }
+
+    let pending_settings = json!({
+        "level-seed": seed.unwrap_or_default(),
+        "level-type": level_type.as_deref().unwrap_or("minecraft:normal"),
+        "generator-settings": generator_settings.unwrap_or("{}"),
+    });
+
+    fs::create_dir_all(&world_path)
+        .and_then(|_| fs::File::create(world_path.join(PENDING_SETTINGS_FILE_NAME)))
+        .and_then(|mut file| file.write_all(pending_settings.to_string().as_bytes()))
+        .map_err(ServmgrError::filesystem("create", &world_path))?;
+
+    println!("{}: Created world '{}', it will be generated the first time the server loads it", style("[INFO]").cyan(), world_name);
+    if select { select_world(world_name) } else { Ok(()) }
+}
+
+pub fn select_world(world_name: &str) -> Result<(), ServmgrError> {
+    let world_path = existing_world_path(world_name)?;
+    require_server_stopped("select a different world")?;
+
+    let server_properties_path = server_properties::server_properties_path();
+    let mut properties = ServerProperties::load_default().map_err(ServmgrError::filesystem("read", &server_properties_path))?;
+
+    properties.set("level-name", &managed_level_name(world_name));
+
+    // generation settings only matter (and are only applied) while the world has not been generated yet, the ones a
+    // world selected earlier was waiting on are reset so the next new world doesn't silently get them too
+    for (key, value) in DEFAULT_GENERATION_SETTINGS { properties.set(key, value); }
+    if !world_path.join("level.dat").exists()
+        && let Ok(pending_settings) = fs::read_to_string(world_path.join(PENDING_SETTINGS_FILE_NAME))
+        && let Ok(Value::Object(pending_settings)) = serde_json::from_str::<Value>(&pending_settings) {
+        for (key, value) in pending_settings {
+            if let Value::String(value) = value { properties.set(&key, &value); }
+        }
+    }
+
+    properties.save().map_err(ServmgrError::filesystem("update", &server_properties_path))?;
+
+    println!("{}: Selected world '{}', it will be loaded the next time the server starts", style("[INFO]").cyan(), world_name);
+    Ok(())
+}
+
+pub fn delete_world(world_name: &str, skip_confirmation: bool) -> Result<(), ServmgrError> {
+    let world_path = existing_world_path(world_name)?;
+    if active_level_name() == managed_level_name(world_name) {
+        return Err(ServmgrError::Invalid(format!("World '{}' is the selected world, select another world before deleting it", world_name)));
+    }
+
+    if !skip_confirmation {
+        print!("Permanently delete world '{}' ({})? [y/N] ", world_name, disk_usage::format_size(disk_usage::directory_size(&world_path)));
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+            println!("{}: Deletion cancelled", style("[INFO]").cyan());
+            return Ok(());
+        }
+    }
+
+    fs::remove_dir_all(&world_path).map_err(ServmgrError::filesystem("delete", &world_path))?;
+
+    println!("{}: Deleted world '{}'", style("[INFO]").cyan(), world_name);
+    Ok(())
+}
+
+pub fn rename_world(world_name: &str, new_world_name: &str) -> Result<(), ServmgrError> {
+    let world_path = existing_world_path(world_name)?;
+    let new_world_path = managed_world_path(new_world_name)?;
+    if new_world_path.exists() {
+        return Err(ServmgrError::Invalid(format!("World '{}' already exists", new_world_name)));
+    }
+
+    let is_active = active_level_name() == managed_level_name(world_name);
+    if is_active { require_server_stopped("rename the selected world")?; }
+
+    fs::rename(&world_path, &new_world_path).map_err(ServmgrError::filesystem("rename", &world_path))?;
+
+    if is_active {
+        ServerProperties::load_default()
+            .and_then(|mut properties| {
+                properties.set("level-name", &managed_level_name(new_world_name));
+                properties.save()
+            })
+            .map_err(ServmgrError::filesystem("update level-name in", &server_properties::server_properties_path()))?;
+    }
+
+    println!("{}: Renamed world '{}' to '{}'", style("[INFO]").cyan(), world_name, new_world_name);
+    Ok(())
+}
+
+pub fn show_world_info(world_name: &str) -> Result<(), ServmgrError> {
+    let world_path = existing_world_path(world_name)?;
+    let is_active = active_level_name() == managed_level_name(world_name);
+
+    println!("{}", style(format!("World '{}'", world_name)).cyan().bold());
+    println!("  {:<14} {}", "Path:", world_path.display());
+    println!("  {:<14} {}", "Selected:", if is_active { "yes" } else { "no" });
+    println!("  {:<14} {}", "Size on disk:", disk_usage::format_size(disk_usage::directory_size(&world_path)));
+
+    let level_dat = match fs::File::open(world_path.join("level.dat")) {
+        Ok(level_dat) => level_dat,
+        Err(_) => {
+            println!("  {:<14} not generated yet (generated the first time the server loads it)", "Status:");
+            if let Ok(pending_settings) = fs::read_to_string(world_path.join(PENDING_SETTINGS_FILE_NAME)) {
+                println!("  {:<14} {}", "Settings:", pending_settings);
+            }
+            return Ok(());
+        },
+    };
+
+    let metadata = read_level_metadata(level_dat).map_err(ServmgrError::filesystem("read", &world_path.join("level.dat")))?;
+    let unknown = || String::from("unknown");
+    println!("  {:<14} {}", "Level name:", metadata.level_name.unwrap_or_else(unknown));
+    println!("  {:<14} {}", "Seed:", metadata.seed.map(|seed| seed.to_string()).unwrap_or_else(unknown));
+    println!("  {:<14} {}", "Last played:", metadata.last_played
+        .map(|last_played| last_played.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(unknown));
+    println!("  {:<14} {}", "Game version:", metadata.game_version.unwrap_or_else(unknown));
+    println!("  {:<14} {}", "Data version:", metadata.data_version.map(|version| version.to_string()).unwrap_or_else(unknown));
+    println!("  {:<14} {}", "Hardcore:", metadata.hardcore.map(|hardcore| if hardcore { "yes" } else { "no" }).unwrap_or("unknown"));
+    Ok(())
+}